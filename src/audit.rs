@@ -0,0 +1,169 @@
+use std::io;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::fileread::{read_log_lines, DayCollection, DayCollector, LogLine, ReadOptions};
+use crate::print::DurationFormat;
+use crate::taskregistry::OpenDayPolicy;
+use crate::timelog::{LogEvent, TimelogEntry};
+
+/// Thresholds above which work times are reported as suspicious.
+#[derive(Debug, Clone)]
+pub struct AuditOptions {
+    pub max_session: Duration,
+    pub max_unassigned: Duration,
+    pub duration_format: DurationFormat,
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        AuditOptions {
+            max_session: Duration::from_secs(10 * 3600),
+            max_unassigned: Duration::from_secs(3600),
+            duration_format: DurationFormat::HoursMinutes,
+        }
+    }
+}
+
+/// A suspicious pattern, along with the line it refers to.
+struct Finding {
+    line_nr: usize,
+    date: NaiveDate,
+    message: String,
+}
+
+/// Lists suspicious patterns in the log: forgotten `off` entries on past days, overly long
+/// sessions, tasks shorter than a minute, much unassigned work time, and days without tasks.
+/// Writes one line per finding, prefixed with the file and line number it refers to.
+pub fn audit(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    options: &AuditOptions,
+) -> Result<(), String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+
+    // Days without "off" are closed at their last entry, so that they can be reported instead of
+    // making the log unreadable.
+    let read_options = ReadOptions {
+        open_days: OpenDayPolicy::LastEntry,
+        ..read.clone()
+    };
+    let mut count = 0;
+    for day in DayCollector::with_options(lines, &read_options) {
+        let day = day?;
+        for finding in audit_day(&day, options) {
+            writeln!(
+                &mut w,
+                "{}:{}: {}: {}",
+                path, finding.line_nr, finding.date, finding.message
+            )
+            .map_err(|err| err.to_string())?;
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        writeln!(&mut w, "No issues found").map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn audit_day(day: &DayCollection, options: &AuditOptions) -> Vec<Finding> {
+    let entries: Vec<(usize, &TimelogEntry)> = day
+        .lines
+        .iter()
+        .filter_map(|(n, line)| match line {
+            LogLine::Entry(entry) => match entry.event {
+                LogEvent::Estimate { .. } => None,
+                _ => Some((*n, entry)),
+            },
+            LogLine::Ignored(_) => None,
+        })
+        .collect();
+    let (first_nr, first_entry) = match entries.first() {
+        Some(&first) => first,
+        None => return Vec::new(),
+    };
+    let date = first_entry.time.naive_local().date();
+
+    let mut findings = Vec::new();
+    let mut report = |line_nr: usize, message: String| {
+        findings.push(Finding {
+            line_nr,
+            date,
+            message,
+        })
+    };
+
+    let (last_nr, last_entry) = entries[entries.len() - 1];
+    if !day.warnings.is_empty() {
+        report(
+            last_nr,
+            format!(
+                "day was never ended, \"off\" is missing after {}",
+                last_entry.time.format("%R")
+            ),
+        );
+    }
+
+    let mut session_start: Option<(usize, DateTime<FixedOffset>)> = None;
+    for (i, &(n, entry)) in entries.iter().enumerate() {
+        match &entry.event {
+            LogEvent::On | LogEvent::Resume | LogEvent::Start(_) if session_start.is_none() => {
+                session_start = Some((n, entry.time));
+            }
+            LogEvent::Off(_) => {
+                if let Some((start_nr, start)) = session_start.take() {
+                    let duration = (entry.time - start).to_std().unwrap_or_default();
+                    if duration > options.max_session {
+                        report(
+                            start_nr,
+                            format!(
+                                "session from {} to {} lasts {}, more than {}",
+                                start.format("%R"),
+                                entry.time.format("%R"),
+                                options.duration_format.format(&duration),
+                                options.duration_format.format(&options.max_session)
+                            ),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let LogEvent::Start(name) = &entry.event {
+            let next = entries[i + 1..]
+                .iter()
+                .find(|(_, next)| !matches!(next.event, LogEvent::Rename { .. }));
+            if let Some((_, next)) = next {
+                if next.time == entry.time {
+                    report(n, format!("task \"{}\" lasts less than a minute", name));
+                }
+            }
+        }
+    }
+
+    let tasks = day.tasks.get_tasks();
+    if let Some(unassigned) = tasks.iter().find(|task| day.tasks.is_undefined_task(task)) {
+        if unassigned.duration > options.max_unassigned {
+            report(
+                first_nr,
+                format!(
+                    "{} of work time is not assigned to a task, more than {}",
+                    options.duration_format.format(&unassigned.duration),
+                    options.duration_format.format(&options.max_unassigned)
+                ),
+            );
+        }
+    }
+    if tasks.iter().all(|task| day.tasks.is_pseudo_task(task)) {
+        report(first_nr, "no tasks recorded".to_owned());
+    }
+
+    findings.sort_by_key(|finding| finding.line_nr);
+    findings
+}
@@ -1,22 +1,51 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::BufRead;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::fileread::{DayCollector, LogLines};
+use chrono::{DateTime, Datelike, FixedOffset, IsoWeek, NaiveDate};
+
+use crate::fileread::{
+    read_log_lines, DayCollection, DayCollector, LogLine, LogLines, ReadOptions,
+};
+use crate::filewrite;
+use crate::focus;
+use crate::focus::Focus;
 use crate::print;
+use crate::print::PrintOptions;
+use crate::projection;
+use crate::projection::Projection;
+use crate::taskregistry::{TaskRegistry, TaskTotals};
+use crate::timelog::{LogEvent, TimelogEntry};
 
+#[derive(Clone, Copy)]
 pub enum SummaryScope {
     All,
     Last(usize),
 }
 
-pub fn last_active(mut w: impl io::Write, path: &str) -> Result<(), String> {
+/// Inclusive range of dates; open ends are unbounded.
+#[derive(Clone, Copy, Default)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| from <= date) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+pub fn last_active(mut w: impl io::Write, path: &str, read: &ReadOptions) -> Result<(), String> {
     let file =
         File::open(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
     let lines = io::BufReader::new(file).lines();
     let lines = LogLines::new(lines);
-    let day_collector = DayCollector::new(lines);
+    let day_collector = collect_days(lines, read);
 
     let last_active = match day_collector.last() {
         Some(day_result) => {
@@ -26,44 +55,211 @@ pub fn last_active(mut w: impl io::Write, path: &str) -> Result<(), String> {
         None => None,
     };
 
-    if last_active.is_some() {
-        writeln!(&mut w, "{}", last_active.unwrap().name).map_err(map_io_err)?;
+    if let Some(task) = last_active {
+        writeln!(&mut w, "{}", task.name).map_err(map_io_err)?;
     }
 
     Ok(())
 }
 
-pub fn tasks(mut w: impl io::Write, path: &str) -> Result<(), String> {
-    let file =
-        File::open(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
-    let lines = io::BufReader::new(file).lines();
-    let lines = LogLines::new(lines);
-    let day_collector = DayCollector::new(lines);
+/// Appends an entry with the given event at the given time. The entry is refused if it is not a
+/// valid continuation of the log, e.g. if it is earlier than the last entry.
+pub fn append(
+    path: &str,
+    read: &ReadOptions,
+    event: LogEvent,
+    time: &DateTime<FixedOffset>,
+) -> Result<(), String> {
+    let entry = TimelogEntry::new(time, event);
+    filewrite::append_entry(path, &entry, read)
+}
 
-    match day_collector.last() {
-        Some(day_result) => {
-            let registry = day_result?.tasks;
-            print::tasks(&mut w, &registry).map_err(map_io_err)?;
+/// Inserts an entry into the log at its chronological position, which may be in a past day.
+pub fn insert(path: &str, read: &ReadOptions, entry: &TimelogEntry) -> Result<(), String> {
+    filewrite::insert_entry(path, entry, read)
+}
+
+/// Continues working on the last active task, or resumes work if there is none. If the last
+/// recorded day lies before the given time, a new day is started first.
+pub fn continue_work(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    time: &DateTime<FixedOffset>,
+) -> Result<(), String> {
+    let last_day = match read_log_lines(path) {
+        Ok(lines) => collect_days(lines, read).last().transpose()?,
+        Err(_) => None,
+    };
+
+    let mut entries = Vec::new();
+    let last_active = match last_day {
+        None => {
+            entries.push(TimelogEntry::new(time, LogEvent::On));
+            None
+        }
+        Some(day) => {
+            let tasks = day.tasks;
+            let date = tasks.get_start_time()?.date().naive_local();
+            let new_day = date < time.date().naive_local();
+            match tasks.get_active() {
+                Some(_) if new_day => {
+                    return Err(format!("Work day {} has not been finished", date));
+                }
+                Some(task) if tasks.is_undefined_task(task) => {
+                    return Err("Already working without an active task".to_owned());
+                }
+                Some(task) => return Err(format!("Task \"{}\" is already active", task.name)),
+                None => {}
+            }
+            if new_day {
+                entries.push(TimelogEntry::new(time, LogEvent::On));
+            }
+            tasks.get_last_active()
         }
-        None => {}
     };
 
+    let message = match last_active {
+        Some(task) => {
+            let message = format!("Continuing task \"{}\"", task.name);
+            entries.push(TimelogEntry::new(time, LogEvent::Start(task.name)));
+            message
+        }
+        None if entries.is_empty() => {
+            entries.push(TimelogEntry::new(time, LogEvent::Resume));
+            "No previous active task, resuming work".to_owned()
+        }
+        None => "No previous active task, starting new day".to_owned(),
+    };
+
+    filewrite::append_entries(path, &entries, read)?;
+    writeln!(&mut w, "{}", message).map_err(map_io_err)
+}
+
+/// Lists the names of all tasks that were started or estimated, most recently used first.
+pub fn complete_tasks(mut w: impl io::Write, path: &str) -> Result<(), String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+
+    let mut names: Vec<String> = Vec::new();
+    let mut current: Option<String> = None;
+    for (_, line) in lines {
+        let entry = match line? {
+            LogLine::Entry(entry) => entry,
+            LogLine::Ignored(_) => continue,
+        };
+        match entry.event {
+            LogEvent::Start(name) => {
+                names.retain(|n| *n != name);
+                names.push(name.clone());
+                current = Some(name);
+            }
+            LogEvent::Rename { to, from } => {
+                if let Some(renamed) = from.or_else(|| current.clone()) {
+                    names.retain(|n| *n != renamed && *n != to);
+                    if current.as_ref() == Some(&renamed) {
+                        current = Some(to.clone());
+                    }
+                }
+                names.push(to);
+            }
+            LogEvent::Estimate { name, .. } if !names.contains(&name) => names.insert(0, name),
+            LogEvent::Stop | LogEvent::Off(_) => current = None,
+            _ => {}
+        }
+    }
+
+    for name in names.iter().rev() {
+        writeln!(&mut w, "{}", name).map_err(map_io_err)?;
+    }
+
+    Ok(())
+}
+
+pub fn tasks(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    if let Some((day, totals, _)) = last_day_with_totals(path, read)? {
+        print::tasks(&mut w, &day.tasks, &totals, options).map_err(map_io_err)?;
+    }
+
+    Ok(())
+}
+
+pub fn status(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    target: &Duration,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    if let Some((day, totals, week)) = last_day_with_totals(path, read)? {
+        print::status(&mut w, &day.tasks, &totals, options).map_err(map_io_err)?;
+        if let Some(projection) = project_today(&day, &week, read, target) {
+            print::projection(&mut w, &projection, options).map_err(map_io_err)?;
+        }
+    }
+
     Ok(())
 }
 
-pub fn summaries(mut w: impl io::Write, path: &str, scope: SummaryScope) -> Result<(), String> {
+/// Shows when today's work day reaches the daily target and evens out the balance of the week.
+pub fn leave(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    target: &Duration,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    let projection = last_day_with_totals(path, read)?
+        .and_then(|(day, _, week)| project_today(&day, &week, read, target));
+    match projection {
+        Some(projection) => print::projection(&mut w, &projection, options).map_err(map_io_err),
+        None => writeln!(&mut w, "No work day started today").map_err(map_io_err),
+    }
+}
+
+/// Projects the end of the given day, if it is today's.
+fn project_today(
+    day: &DayCollection,
+    week: &[Duration],
+    read: &ReadOptions,
+    target: &Duration,
+) -> Option<Projection> {
+    let now = read.clock.now();
+    let start = day.tasks.get_start_time().ok()?;
+    if start.date().naive_local() != now.date().naive_local() {
+        return None;
+    }
+    let week_balance = projection::balance(week, target);
+    Some(projection::project(&day.tasks, week_balance, target, &now))
+}
+
+pub fn summaries(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    scope: SummaryScope,
+    options: &PrintOptions,
+) -> Result<(), String> {
     let file =
         File::open(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
     let lines = io::BufReader::new(file).lines();
     let lines = LogLines::new(lines);
-    let day_collector = DayCollector::new(lines);
+    let day_collector = collect_days(lines, read);
+    let mut totals = TaskTotals::new();
 
+    print::document_start(&mut w, "Summary", options.format).map_err(map_io_err)?;
     match scope {
         SummaryScope::All => {
             for day in day_collector {
                 let day = day?;
-                print::day_summary(&mut w, &day.tasks).map_err(map_io_err)?;
-                writeln!(&mut w).map_err(map_io_err)?;
+                totals.add(&day.tasks);
+                print::day_summary(&mut w, &day.tasks, &totals, options).map_err(map_io_err)?;
+                day_separator(&mut w, options)?;
             }
         }
         SummaryScope::Last(n) => {
@@ -71,34 +267,41 @@ pub fn summaries(mut w: impl io::Write, path: &str, scope: SummaryScope) -> Resu
 
             for day in day_collector {
                 let day = day?;
+                totals.add(&day.tasks);
                 if day_tasks.len() == n {
                     day_tasks.pop_front();
                 }
-                day_tasks.push_back(day.tasks);
+                day_tasks.push_back((day.tasks, totals.clone()));
             }
 
-            for tasks in day_tasks {
-                print::day_summary(&mut w, &tasks).map_err(map_io_err)?;
-                writeln!(&mut w).map_err(map_io_err)?;
+            for (tasks, totals) in day_tasks {
+                print::day_summary(&mut w, &tasks, &totals, options).map_err(map_io_err)?;
+                day_separator(&mut w, options)?;
             }
         }
     };
-
-    Ok(())
+    print::document_end(&mut w, options.format).map_err(map_io_err)
 }
 
-pub fn worklog(mut w: impl io::Write, path: &str, scope: SummaryScope) -> Result<(), String> {
+pub fn worklog(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    scope: SummaryScope,
+    options: &PrintOptions,
+) -> Result<(), String> {
     let file =
         File::open(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
     let lines = io::BufReader::new(file).lines();
     let lines = LogLines::new(lines);
-    let day_collector = DayCollector::new(lines);
+    let day_collector = collect_days(lines, read);
 
+    print::document_start(&mut w, "Work log", options.format).map_err(map_io_err)?;
     match scope {
         SummaryScope::All => {
             for day in day_collector {
                 let day = day?;
-                print::worklog(&mut w, &day.tasks).map_err(map_io_err)?;
+                print::worklog(&mut w, &day.tasks, options).map_err(map_io_err)?;
                 writeln!(&mut w).map_err(map_io_err)?;
             }
         }
@@ -114,12 +317,300 @@ pub fn worklog(mut w: impl io::Write, path: &str, scope: SummaryScope) -> Result
             }
 
             for tasks in day_tasks {
-                print::worklog(&mut w, &tasks).map_err(map_io_err)?;
+                print::worklog(&mut w, &tasks, options).map_err(map_io_err)?;
                 writeln!(&mut w).map_err(map_io_err)?;
             }
         }
     };
+    print::document_end(&mut w, options.format).map_err(map_io_err)
+}
+
+/// Displays task totals over all days within the given range.
+pub fn report(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    range: &DateRange,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    let report = collect_report(path, read, range)?;
+    let heading = range_heading(range);
+    print::document_start(&mut w, &heading, options.format).map_err(map_io_err)?;
+    print::task_totals(
+        &mut w,
+        &heading,
+        &report.totals,
+        &report.work_duration,
+        report.days,
+        options,
+    )
+    .map_err(map_io_err)?;
+    print::document_end(&mut w, options.format).map_err(map_io_err)
+}
+
+/// Displays the ISO week starting on the given Monday, with one column per day.
+pub fn week(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    monday: NaiveDate,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    let mut days: Vec<(NaiveDate, Option<TaskRegistry>)> = monday
+        .iter_days()
+        .take(7)
+        .map(|date| (date, None))
+        .collect();
+
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    for day in collect_days(lines, read) {
+        let tasks = day?.tasks;
+        let date = tasks.get_start_time()?.date().naive_local();
+        if let Some((_, registry)) = days.iter_mut().find(|(d, _)| *d == date) {
+            match registry {
+                Some(registry) => registry.merge(&tasks),
+                None => *registry = Some(tasks),
+            }
+        }
+    }
+
+    let week = monday.iso_week();
+    let heading = format!(
+        "Week {}-W{:02}, {} – {}",
+        week.year(),
+        week.week(),
+        monday,
+        days[6].0
+    );
+    print::document_start(&mut w, &heading, options.format).map_err(map_io_err)?;
+    print::week(&mut w, &heading, &days, options).map_err(map_io_err)?;
+    print::document_end(&mut w, options.format).map_err(map_io_err)
+}
+
+/// Displays a heatmap of the work time per day of the given year.
+pub fn heatmap(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    year: i32,
+    target: &Duration,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    let mut work = HashMap::new();
+    let mut logged: Option<(NaiveDate, NaiveDate)> = None;
+    for day in collect_days(lines, read) {
+        let tasks = day?.tasks;
+        let date = tasks.get_start_time()?.date().naive_local();
+        logged = Some((logged.map_or(date, |(first, _)| first), date));
+        if date.year() == year {
+            *work.entry(date).or_insert_with(|| Duration::from_secs(0)) +=
+                tasks.get_work_duration();
+        }
+    }
+
+    print::heatmap(&mut w, year, &work, logged, target, options).map_err(map_io_err)
+}
+
+/// Displays focus metrics for the days in the range: task switches and the length of
+/// uninterrupted stints on a task, per day and per week.
+pub fn focus(
+    mut w: impl io::Write,
+    path: &str,
+    read: &ReadOptions,
+    range: &DateRange,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    let mut days = Vec::new();
+    for day in collect_days(lines, read) {
+        let day = day?;
+        let date = day.tasks.get_start_time()?.date().naive_local();
+        if range.contains(date) {
+            days.push((date, Focus::of_day(&focus::stints(&day))));
+        }
+    }
+
+    let heading = format!("Focus {}", range_heading(range));
+    print::document_start(&mut w, &heading, options.format).map_err(map_io_err)?;
+    print::focus(&mut w, &heading, &days, options).map_err(map_io_err)?;
+    print::document_end(&mut w, options.format).map_err(map_io_err)
+}
+
+/// Displays task totals per person and combined for the whole team. Each member is given as a
+/// pair of a label and a file path.
+pub fn team(
+    mut w: impl io::Write,
+    members: &[(String, String)],
+    read: &ReadOptions,
+    range: &DateRange,
+    options: &PrintOptions,
+) -> Result<(), String> {
+    let mut team_totals = TaskTotals::new();
+    let mut team_work_duration = Duration::from_secs(0);
+    let mut team_days = 0;
+
+    let team_heading = format!("Team {}", range_heading(range));
+    print::document_start(&mut w, &team_heading, options.format).map_err(map_io_err)?;
+
+    for (label, path) in members {
+        let report = collect_report(path, read, range)?;
+        let heading = format!("{} {}", label, range_heading(range));
+        print::task_totals(
+            &mut w,
+            &heading,
+            &report.totals,
+            &report.work_duration,
+            report.days,
+            options,
+        )
+        .map_err(map_io_err)?;
+        writeln!(&mut w).map_err(map_io_err)?;
+
+        team_totals.add_totals(&report.totals);
+        team_work_duration += report.work_duration;
+        team_days += report.days;
+    }
+
+    print::task_totals(
+        &mut w,
+        &team_heading,
+        &team_totals,
+        &team_work_duration,
+        team_days,
+        options,
+    )
+    .map_err(map_io_err)?;
+    print::document_end(&mut w, options.format).map_err(map_io_err)
+}
+
+/// Renders output repeatedly, redrawing the terminal whenever the file is modified or a new minute
+/// starts, so that the time of an active task keeps ticking up. Runs until interrupted.
+pub fn watch<F>(mut w: impl io::Write, path: &str, mut render: F) -> Result<(), String>
+where
+    F: FnMut(&mut Vec<u8>, &str) -> Result<(), String>,
+{
+    let mut last_change = None;
+    loop {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+        let minute = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| err.to_string())?
+            .as_secs()
+            / 60;
+
+        if last_change != Some((modified, minute)) {
+            last_change = Some((modified, minute));
+
+            let mut buf = Vec::new();
+            if let Err(err) = render(&mut buf, path) {
+                buf = format!("{}\n", err).into_bytes();
+            }
+            write!(&mut w, "\x1b[2J\x1b[H").map_err(map_io_err)?;
+            w.write_all(&buf).map_err(map_io_err)?;
+            w.flush().map_err(map_io_err)?;
+        }
+
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/// Reads the last date, along with the task totals of all days and the work times of the
+/// previous dates in the last date's week. All parts of the last date are combined into one day.
+fn last_day_with_totals(
+    path: &str,
+    read: &ReadOptions,
+) -> Result<Option<(DayCollection, TaskTotals, Vec<Duration>)>, String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    let mut totals = TaskTotals::new();
+    let mut last_day: Option<DayCollection> = None;
+    let mut week = Vec::new();
+    for day in collect_days(lines, read) {
+        let day = day?;
+        totals.add(&day.tasks);
+        match &mut last_day {
+            Some(last_day) if last_day.date().is_some() && last_day.date() == day.date() => {
+                last_day.merge(day)
+            }
+            _ => {
+                if let Some(last_day) = &last_day {
+                    if iso_week(last_day).is_some() && iso_week(last_day) == iso_week(&day) {
+                        week.push(last_day.tasks.get_work_duration());
+                    } else {
+                        week.clear();
+                    }
+                }
+                last_day = Some(day);
+            }
+        }
+    }
+
+    Ok(last_day.map(|day| (day, totals, week)))
+}
+
+fn iso_week(day: &DayCollection) -> Option<IsoWeek> {
+    day.date().map(|date| date.iso_week())
+}
 
+/// Collects the days of the log, printing a warning for every day that was closed automatically.
+fn collect_days<I>(
+    lines: I,
+    read: &ReadOptions,
+) -> impl Iterator<Item = Result<DayCollection, String>>
+where
+    I: Iterator<Item = (usize, Result<LogLine, String>)>,
+{
+    DayCollector::with_options(lines, read).inspect(|day| {
+        if let Ok(day) = day {
+            for warning in &day.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+        }
+    })
+}
+
+struct Report {
+    totals: TaskTotals,
+    work_duration: Duration,
+    days: usize,
+}
+
+fn collect_report(path: &str, read: &ReadOptions, range: &DateRange) -> Result<Report, String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    let mut report = Report {
+        totals: TaskTotals::new(),
+        work_duration: Duration::from_secs(0),
+        days: 0,
+    };
+    for day in collect_days(lines, read) {
+        let tasks = day?.tasks;
+        if range.contains(tasks.get_start_time()?.date().naive_local()) {
+            report.totals.add(&tasks);
+            report.work_duration += tasks.get_work_duration();
+            report.days += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+fn range_heading(range: &DateRange) -> String {
+    let format = |date: Option<NaiveDate>| date.map_or("…".to_owned(), |d| d.to_string());
+    format!("{} – {}", format(range.from), format(range.to))
+}
+
+/// Separates day summaries by a blank line, unless a template defines the layout.
+fn day_separator(mut w: impl io::Write, options: &PrintOptions) -> Result<(), String> {
+    if options.template.is_none() {
+        writeln!(&mut w).map_err(map_io_err)?;
+    }
     Ok(())
 }
 
@@ -2,9 +2,12 @@ use std::fs::File;
 use std::io;
 use std::io::BufRead;
 use std::path::Path;
+use std::str::FromStr;
 
-use crate::taskregistry::{TaskRegistry, TaskRegistryBuilder};
-use crate::timelog::TimelogEntry;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::taskregistry::{OpenDayPolicy, PseudoNames, TaskRegistry, TaskRegistryBuilder};
+use crate::timelog::{Clock, TimelogEntry};
 use std::fmt::Display;
 use std::iter::Enumerate;
 
@@ -14,8 +17,10 @@ pub enum LogLine {
     Ignored(String),
 }
 
-impl LogLine {
-    pub fn from_str(line: &str) -> Result<LogLine, String> {
+impl FromStr for LogLine {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<LogLine, String> {
         if line.is_empty() || line.starts_with('#') {
             return Ok(LogLine::Ignored(line.to_owned()));
         }
@@ -63,6 +68,33 @@ where
 pub struct DayCollection {
     pub tasks: TaskRegistry,
     pub lines: Vec<(usize, LogLine)>,
+    /// Warnings about the day, e.g. if it was closed automatically.
+    pub warnings: Vec<String>,
+}
+
+impl DayCollection {
+    /// Returns the date the day started on.
+    pub fn date(&self) -> Option<NaiveDate> {
+        let start = self.tasks.get_start_time().ok()?;
+        Some(start.date().naive_local())
+    }
+
+    /// Appends a later part of the same date, e.g. after an `off` followed by `on`.
+    pub fn merge(&mut self, other: DayCollection) {
+        self.tasks.merge(&other.tasks);
+        self.lines.extend(other.lines);
+        self.warnings.extend(other.warnings);
+    }
+}
+
+/// Options for reading days from a log.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    pub open_days: OpenDayPolicy,
+    /// The time a day in progress is accounted up to. With a fixed clock, later entries are
+    /// ignored, showing the log as it was at that time.
+    pub clock: Clock,
+    pub pseudo_names: PseudoNames,
 }
 
 pub struct DayCollector<I> {
@@ -71,6 +103,8 @@ pub struct DayCollector<I> {
     done: bool,
     buffer: Vec<(usize, LogLine)>,
     lookahead: usize,
+    last_entry: Option<(usize, TimelogEntry)>,
+    until: Option<DateTime<FixedOffset>>,
 }
 
 impl<I, E> DayCollector<I>
@@ -79,12 +113,25 @@ where
     E: Display,
 {
     pub fn new(it: I) -> DayCollector<I> {
+        DayCollector::with_options(it, &ReadOptions::default())
+    }
+
+    pub fn with_options(it: I, options: &ReadOptions) -> DayCollector<I> {
         DayCollector {
             it,
-            builder: TaskRegistryBuilder::new(),
+            builder: TaskRegistryBuilder::with_options(
+                options.open_days,
+                options.clock,
+                options.pseudo_names.clone(),
+            ),
             done: false,
             buffer: Vec::new(),
             lookahead: 0,
+            last_entry: None,
+            until: match options.clock {
+                Clock::System => None,
+                Clock::Fixed(time) => Some(time),
+            },
         }
     }
 
@@ -92,9 +139,11 @@ where
         self.done = true;
         let lines: Vec<(usize, LogLine)> = self.buffer.drain(..).collect();
         if !lines.is_empty() {
+            let tasks = self.builder.finish();
             let result = DayCollection {
-                tasks: self.builder.finish(),
+                tasks,
                 lines,
+                warnings: self.builder.take_warnings(),
             };
             Some(Ok(result))
         } else {
@@ -107,27 +156,29 @@ where
         n: usize,
         log_line: LogLine,
     ) -> Option<Result<DayCollection, String>> {
-        self.buffer.push((n + 1, log_line.clone()));
+        self.buffer.push((n, log_line.clone()));
 
         match log_line {
             LogLine::Entry(entry) => {
+                if let Err(err) = check_order(n, &entry, self.last_entry.as_ref()) {
+                    return Some(Err(err));
+                }
+                self.last_entry = Some((n, entry.clone()));
+
                 let result = match self.builder.add_entry(&entry) {
                     Err(err) => Some(Err(format!(
                         "{} (while processing {:?} in line {})",
                         err, entry, n
                     ))),
-                    Ok(tasks_opt) => tasks_opt
-                        .map(|tasks| {
-                            let len = self.buffer.len() - self.lookahead - 1;
-                            let lines: Vec<(usize, LogLine)> = self.buffer.drain(..len).collect();
-                            let result = DayCollection {
-                                tasks: tasks.clone(),
-                                lines,
-                            };
-
-                            result
+                    Ok(tasks_opt) => tasks_opt.map(|tasks| {
+                        let len = self.buffer.len() - self.lookahead - 1;
+                        let lines: Vec<(usize, LogLine)> = self.buffer.drain(..len).collect();
+                        Ok(DayCollection {
+                            tasks,
+                            lines,
+                            warnings: self.builder.take_warnings(),
                         })
-                        .map(|res| Ok(res)),
+                    }),
                 };
 
                 self.lookahead = 0;
@@ -162,6 +213,11 @@ where
                 None => return self.process_eof(),
                 Some((n, line_res)) => match line_res {
                     Err(err) => return Some(Err(format!("Input error on line {}: {}", n, err))),
+                    Ok(LogLine::Entry(entry))
+                        if self.until.is_some_and(|until| entry.time > until) =>
+                    {
+                        return self.process_eof()
+                    }
                     Ok(line) => (n, line),
                 },
             };
@@ -181,3 +237,18 @@ where
     let file = File::open(filename)?;
     Ok(LogLines::new(io::BufReader::new(file).lines()))
 }
+
+/// Checks that an entry is not earlier than the previous one, given along with its line number.
+pub(crate) fn check_order(
+    n: usize,
+    entry: &TimelogEntry,
+    last: Option<&(usize, TimelogEntry)>,
+) -> Result<(), String> {
+    match last {
+        Some((last_n, last_entry)) if entry.time < last_entry.time => Err(format!(
+            "Timestamp in line {} is earlier than the one in line {}: \"{}\" after \"{}\"",
+            n, last_n, entry, last_entry
+        )),
+        _ => Ok(()),
+    }
+}
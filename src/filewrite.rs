@@ -0,0 +1,186 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Write};
+
+use crate::fileread::{check_order, LogLine, LogLines, ReadOptions};
+use crate::taskregistry::TaskRegistryBuilder;
+use crate::timelog::{LogEvent, TimelogEntry};
+
+/// Appends an entry to the log file, after checking that it is a valid continuation of the
+/// entries already recorded.
+pub fn append_entry(path: &str, entry: &TimelogEntry, read: &ReadOptions) -> Result<(), String> {
+    append_entries(path, std::slice::from_ref(entry), read)
+}
+
+/// Appends several entries to the log file. Nothing is written unless all of them are valid.
+///
+/// The log is locked while it is checked and written, so that concurrent invocations cannot
+/// interleave. The new lines are written with a single call on a file opened for appending.
+/// The open day policy and pseudo-task names of the read options apply to the check.
+pub fn append_entries(
+    path: &str,
+    entries: &[TimelogEntry],
+    read: &ReadOptions,
+) -> Result<(), String> {
+    let lock = lock_log(path)?;
+    let result = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|err| format!("Could not open file {:?}: {}", path, err))
+        .and_then(|mut file| check_and_append(&mut file, path, entries, read));
+    let _ = lock.unlock();
+    result
+}
+
+fn check_and_append(
+    file: &mut File,
+    path: &str,
+    entries: &[TimelogEntry],
+    read: &ReadOptions,
+) -> Result<(), String> {
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    if !content.is_empty() && !content.ends_with('\n') {
+        return Err(format!(
+            "File {:?} does not end with a line break, its last line may be incomplete",
+            path
+        ));
+    }
+
+    let (mut builder, mut last_entry) = replay(&content, read)?;
+    let mut text = String::new();
+    for entry in entries {
+        if let Some(last_entry) = &last_entry {
+            if entry.time < last_entry.time {
+                return Err(format!(
+                    "Cannot add \"{}\": it is earlier than the last entry \"{}\"",
+                    entry, last_entry
+                ));
+            }
+        }
+        builder
+            .add_entry(entry)
+            .map_err(|err| format!("Cannot add \"{}\": {}", entry, err))?;
+        last_entry = Some(entry.clone());
+
+        if entry.event == LogEvent::On {
+            text.push_str(&format!("\n# {}\n", entry.time.format("%A")));
+        }
+        text.push_str(&format!("{}\n", entry));
+    }
+
+    file.write_all(text.as_bytes())
+        .map_err(|err| format!("Could not write to file {:?}: {}", path, err))
+}
+
+/// Inserts an entry at its chronological position, after all entries that are not later than it.
+/// Comment and blank lines stay where they are, so the entry goes into the right day.
+///
+/// Only the resulting log is validated, so an entry may repair an invalid day, e.g. add a
+/// missing `off`. If the result is invalid, the file is left untouched. The new content is
+/// written to a temporary file next to the log, which is then renamed over it, so that the log is
+/// never left half-written. The log stays locked meanwhile, so that concurrent appends wait for
+/// the insertion.
+pub fn insert_entry(path: &str, entry: &TimelogEntry, read: &ReadOptions) -> Result<(), String> {
+    let lock = lock_log(path)?;
+    let result = File::open(path)
+        .map_err(|err| format!("Could not open file {:?}: {}", path, err))
+        .and_then(|mut file| check_and_insert(&mut file, path, entry, read));
+    let _ = lock.unlock();
+    result
+}
+
+/// Locks the log for writing. The lock is held on a separate file next to the log, as the log
+/// itself is replaced when inserting.
+fn lock_log(path: &str) -> Result<File, String> {
+    let lock_path = format!("{}.lock", path);
+    let lock = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|err| format!("Could not open lock file {:?}: {}", lock_path, err))?;
+    lock.lock()
+        .map_err(|err| format!("Could not lock file {:?}: {}", lock_path, err))?;
+    Ok(lock)
+}
+
+fn check_and_insert(
+    file: &mut File,
+    path: &str,
+    entry: &TimelogEntry,
+    read: &ReadOptions,
+) -> Result<(), String> {
+    let mut content = String::new();
+    file.read_to_string(&mut content)
+        .map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut position = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if let Ok(LogLine::Entry(existing)) = line.parse::<LogLine>() {
+            if existing.time <= entry.time {
+                position = i + 1;
+            }
+        }
+    }
+
+    let mut text = String::new();
+    for line in &lines[..position] {
+        text.push_str(line);
+        text.push('\n');
+    }
+    if entry.event == LogEvent::On {
+        text.push_str(&format!("\n# {}\n", entry.time.format("%A")));
+    }
+    text.push_str(&format!("{}\n", entry));
+    for line in &lines[position..] {
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    replay(&text, read).map_err(|err| format!("Cannot insert \"{}\": {}", entry, err))?;
+
+    let temp_path = format!("{}.insert.tmp", path);
+    replace_file(file, path, &temp_path, &text).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+fn replace_file(file: &File, path: &str, temp_path: &str, text: &str) -> Result<(), String> {
+    let write_err = |err: io::Error| format!("Could not write file {:?}: {}", temp_path, err);
+    let mut temp_file = File::create(temp_path).map_err(write_err)?;
+    temp_file.write_all(text.as_bytes()).map_err(write_err)?;
+    temp_file.sync_all().map_err(write_err)?;
+    if let Ok(metadata) = file.metadata() {
+        fs::set_permissions(temp_path, metadata.permissions()).map_err(write_err)?;
+    }
+    fs::rename(temp_path, path).map_err(|err| format!("Could not replace file {:?}: {}", path, err))
+}
+
+/// Feeds all entries of the log into a new builder, and returns it along with the last entry.
+fn replay(
+    content: &str,
+    read: &ReadOptions,
+) -> Result<(TaskRegistryBuilder, Option<TimelogEntry>), String> {
+    let mut builder =
+        TaskRegistryBuilder::with_options(read.open_days, read.clock, read.pseudo_names.clone());
+    let mut last_entry = None;
+
+    let lines = LogLines::new(content.lines().map(|line| io::Result::Ok(line.to_owned())));
+    for (n, line) in lines {
+        if let LogLine::Entry(entry) = line? {
+            check_order(n, &entry, last_entry.as_ref())?;
+            builder
+                .add_entry(&entry)
+                .map_err(|err| format!("{} (in line {})", err, n))?;
+            last_entry = Some((n, entry));
+        }
+    }
+
+    Ok((builder, last_entry.map(|(_, entry)| entry)))
+}
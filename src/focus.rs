@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, FixedOffset, IsoWeek, NaiveDate};
+
+use crate::fileread::{DayCollection, LogLine};
+use crate::timelog::LogEvent;
+
+/// Stints shorter than this count as fragmented time.
+pub const SHORT_STINT: Duration = Duration::from_secs(15 * 60);
+
+/// An uninterrupted period of work on a single task.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Stint {
+    pub name: String,
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+impl Stint {
+    pub fn duration(&self) -> Duration {
+        (self.end - self.start).to_std().unwrap_or_default()
+    }
+}
+
+/// Focus metrics over one or more days.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Focus {
+    pub days: usize,
+    pub stints: usize,
+    /// Number of times work continued on another task than before, pauses in between or not.
+    pub switches: usize,
+    pub task_time: Duration,
+    pub longest: Duration,
+    /// Time spent in stints shorter than `SHORT_STINT`.
+    pub short_time: Duration,
+}
+
+impl Focus {
+    pub fn of_day(stints: &[Stint]) -> Focus {
+        let mut focus = Focus {
+            days: 1,
+            stints: stints.len(),
+            switches: stints.windows(2).filter(|s| s[0].name != s[1].name).count(),
+            ..Focus::default()
+        };
+        for stint in stints {
+            let duration = stint.duration();
+            focus.task_time += duration;
+            focus.longest = focus.longest.max(duration);
+            if duration < SHORT_STINT {
+                focus.short_time += duration;
+            }
+        }
+        focus
+    }
+
+    pub fn add(&mut self, other: &Focus) {
+        self.days += other.days;
+        self.stints += other.stints;
+        self.switches += other.switches;
+        self.task_time += other.task_time;
+        self.longest = self.longest.max(other.longest);
+        self.short_time += other.short_time;
+    }
+
+    pub fn average_stint(&self) -> Duration {
+        match self.stints {
+            0 => Duration::from_secs(0),
+            n => self.task_time / n as u32,
+        }
+    }
+
+    pub fn switches_per_day(&self) -> f64 {
+        match self.days {
+            0 => 0.0,
+            n => self.switches as f64 / n as f64,
+        }
+    }
+
+    /// Returns the share of task time spent in short stints, in percent.
+    pub fn short_share(&self) -> u64 {
+        match self.task_time.as_secs() {
+            0 => 0,
+            secs => (self.short_time.as_secs() * 100 + secs / 2) / secs,
+        }
+    }
+}
+
+/// Sums up the focus metrics of the given days per ISO week.
+pub fn by_week(days: &[(NaiveDate, Focus)]) -> Vec<(IsoWeek, Focus)> {
+    let mut weeks: Vec<(IsoWeek, Focus)> = Vec::new();
+    for (date, focus) in days {
+        match weeks.last_mut() {
+            Some((week, total)) if *week == date.iso_week() => total.add(focus),
+            _ => weeks.push((date.iso_week(), focus.clone())),
+        }
+    }
+    weeks
+}
+
+/// Reconstructs the stints of a day from its log entries. Work time without a task is not part
+/// of any stint, and stints of a day still in progress end at its last recorded work time.
+pub fn stints(day: &DayCollection) -> Vec<Stint> {
+    let mut stints: Vec<Stint> = Vec::new();
+    let mut current: Option<(String, DateTime<FixedOffset>)> = None;
+
+    for (_, line) in &day.lines {
+        let entry = match line {
+            LogLine::Entry(entry) => entry,
+            LogLine::Ignored(_) => continue,
+        };
+        match &entry.event {
+            LogEvent::Start(name) => {
+                close(&mut stints, current.take(), entry.time);
+                current = Some((name.to_owned(), entry.time));
+            }
+            LogEvent::Stop | LogEvent::Off(_) => close(&mut stints, current.take(), entry.time),
+            LogEvent::Rename { to, from } => {
+                let name = from
+                    .as_ref()
+                    .or(current.as_ref().map(|(name, _)| name))
+                    .cloned();
+                if let Some(name) = name {
+                    rename(&mut stints, &mut current, &name, to);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((_, end)) = day.tasks.get_work_times().last() {
+        close(&mut stints, current.take(), *end);
+    }
+    stints
+}
+
+/// Ends the current stint, if any, unless it is empty.
+fn close(
+    stints: &mut Vec<Stint>,
+    current: Option<(String, DateTime<FixedOffset>)>,
+    end: DateTime<FixedOffset>,
+) {
+    if let Some((name, start)) = current {
+        if start < end {
+            stints.push(Stint { name, start, end });
+        }
+    }
+}
+
+/// Renames a task in the stints so far, as a rename entry applies to the whole day.
+fn rename(
+    stints: &mut [Stint],
+    current: &mut Option<(String, DateTime<FixedOffset>)>,
+    from: &str,
+    to: &str,
+) {
+    let names = stints
+        .iter_mut()
+        .map(|stint| &mut stint.name)
+        .chain(current.iter_mut().map(|(name, _)| name));
+    for name in names.filter(|name| *name == from) {
+        *name = to.to_owned();
+    }
+}
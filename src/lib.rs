@@ -1,10 +1,19 @@
 extern crate chrono;
 
+pub mod audit;
 pub mod cmd;
 pub mod fileread;
+pub mod filewrite;
+pub mod focus;
+pub mod merge;
 pub mod print;
+pub mod projection;
+pub mod serve;
+pub mod table;
 pub mod taskregistry;
+pub mod template;
 pub mod timelog;
+pub mod timespec;
 
 #[cfg(test)]
 mod tests {
@@ -13,17 +22,22 @@ mod tests {
 
     use chrono::DateTime;
 
-    use crate::fileread::{DayCollection, DayCollector, LogLines};
-    use crate::taskregistry::{Task, TaskRegistry};
+    use crate::fileread::{DayCollection, DayCollector, LogLines, ReadOptions};
+    use crate::focus;
+    use crate::focus::Focus;
+    use crate::print::DurationFormat;
+    use crate::taskregistry::{
+        OpenDayPolicy, PseudoNames, Task, TaskRegistry, TaskTotal, TaskTotals,
+    };
 
-    const BLANK_LINES: &'static str = r#"
+    const BLANK_LINES: &str = r#"
 
 "#;
 
-    const COMMENT_LINE: &'static str = r#"# This is a comment
+    const COMMENT_LINE: &str = r#"# This is a comment
 "#;
 
-    const DAY_1: &'static str = r#"# First line comment
+    const DAY_1: &str = r#"# First line comment
 2019-11-21T07:30+0100	on
 2019-11-21T07:30+0100	start	BACKEND-errors
 2019-11-21T09:45+0100	off
@@ -34,7 +48,7 @@ mod tests {
 2019-11-21T17:00+0100	off
 "#;
 
-    const DAY_2: &'static str = r#"2019-11-22T07:00+0100	on
+    const DAY_2: &str = r#"2019-11-22T07:00+0100	on
 2019-11-22T07:02+0100	start	BACKEND-error-handling
 2019-11-22T07:27+0100	start	BACKEND-input
 2019-11-22T07:30+0100	rename	BACKEND-input-parsing
@@ -57,7 +71,7 @@ mod tests {
 2019-11-22T15:24+0100	off
 "#;
 
-    const DAY_3: &'static str = r#"2019-11-26T07:00+0100	on
+    const DAY_3: &str = r#"2019-11-26T07:00+0100	on
 2019-11-26T07:10+0100	start	FRONTEND - error handling
 2019-11-26T07:34+0100	start	BACKEND - query endpoint
 2019-11-26T07:48+0100	off
@@ -86,7 +100,7 @@ mod tests {
 2019-11-26T17:53+0100	off
 "#;
 
-    const DAY_4: &'static str = r#"2019-11-28T08:55+0100	on
+    const DAY_4: &str = r#"2019-11-28T08:55+0100	on
 2019-11-28T09:08+0100	start	Bugfix Export
 2019-11-28T09:30+0100	start	Sprint planning
 2019-11-28T10:15+0100	start	CHORE - Build system
@@ -159,13 +173,31 @@ mod tests {
         assert_eq!(day2.lines.len(), 24);
     }
 
+    #[test]
+    fn test_line_numbers() {
+        let mut src = String::new();
+        src.push_str(BLANK_LINES);
+        src.push_str(DAY_1);
+
+        let days = into_days(src);
+        let numbers: Vec<usize> = days[0].lines.iter().map(|(n, _)| *n).collect();
+        assert_eq!(numbers, (1..=11).collect::<Vec<_>>());
+
+        let log = "# comment\n\
+                   2019-11-26T07:00+0100\ton\n\
+                   2019-11-26T08:00+0100\ton\n";
+        let lines = log.lines().map(|line| io::Result::Ok(line.to_owned()));
+        let result: Result<Vec<_>, _> = DayCollector::new(LogLines::new(lines)).collect();
+        assert!(result.unwrap_err().ends_with(" in line 3)"));
+    }
+
     #[test]
     fn test_day_1_tasks() {
         let days = into_days(DAY_1);
         assert_eq!(days.len(), 1);
 
         let registry = &days[0].tasks;
-        assert_day1_tasks(&registry);
+        assert_day1_tasks(registry);
     }
 
     #[test]
@@ -219,6 +251,60 @@ mod tests {
         assert_eq!(tasks.get_last_active(), None);
     }
 
+    #[test]
+    fn test_estimates() {
+        let mut src = String::new();
+        src.push_str("2019-11-20T17:00+0100\testimate\tBACKEND-errors\t4:00\n");
+        src.push_str(DAY_1);
+        src.push_str("2019-11-21T17:05+0100\testimate\tBACKEND-input-parsing\t8:00\n");
+        src.push_str(DAY_2);
+
+        let days = into_days(src);
+        assert_eq!(days.len(), 2);
+
+        let mut totals = TaskTotals::new();
+        days.iter().for_each(|day| totals.add(&day.tasks));
+
+        assert_eq!(
+            totals.get("BACKEND-error-handling"),
+            Some(&TaskTotal {
+                name: "BACKEND-error-handling".to_owned(),
+                duration: Duration::from_secs((135 + 82) * 60),
+                estimate: Some(Duration::from_secs(4 * 3600)),
+                pseudo: false,
+            })
+        );
+        assert_eq!(
+            totals.get("BACKEND-input-parsing"),
+            Some(&TaskTotal {
+                name: "BACKEND-input-parsing".to_owned(),
+                duration: Duration::from_secs((320 + 207) * 60),
+                estimate: Some(Duration::from_secs(8 * 3600)),
+                pseudo: false,
+            })
+        );
+        assert_eq!(totals.get("Daily").unwrap().estimate, None);
+    }
+
+    #[test]
+    fn test_estimate_between_days() {
+        let mut src = String::new();
+        src.push_str(DAY_1);
+        src.push_str("2019-11-21T17:05+0100\testimate\tBACKEND-input-parsing\t8:00\n");
+        src.push_str(DAY_2);
+
+        let days = into_days(src);
+        assert_eq!(days.len(), 2);
+        assert!(days[0].tasks.get_estimates().is_empty());
+        assert_eq!(
+            days[1].tasks.get_estimates(),
+            &[(
+                "BACKEND-input-parsing".to_owned(),
+                Duration::from_secs(8 * 3600)
+            )]
+        );
+    }
+
     fn assert_day1_tasks(registry: &TaskRegistry) {
         let expected = [
             Task::new("Pause", 115),
@@ -267,6 +353,230 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_duration_formats() {
+        let duration = Duration::from_secs(7 * 3600 + 45 * 60 + 59);
+        let format = |s: &str| s.parse::<DurationFormat>().unwrap().format(&duration);
+        assert_eq!(format("hh:mm"), "07:45");
+        assert_eq!(format("decimal"), "7.75");
+        assert_eq!(format("decimal:1"), "7.8");
+        assert_eq!(format("decimal:0"), "8");
+        assert_eq!(format("human"), "7h45m");
+
+        let human = DurationFormat::Human;
+        assert_eq!(human.format(&Duration::from_secs(45 * 60)), "45m");
+        assert_eq!(human.format(&Duration::from_secs(2 * 3600)), "2h");
+
+        assert!("decimal:x".parse::<DurationFormat>().is_err());
+        assert!("decimal:7".parse::<DurationFormat>().is_err());
+        assert!("hours".parse::<DurationFormat>().is_err());
+    }
+
+    #[test]
+    fn test_non_monotonic_timestamps() {
+        let log = "2019-11-26T07:00+0100\ton\n\
+                   # comment\n\
+                   2019-11-26T09:00+0100\tstart\tA\n\
+                   2019-11-26T08:00+0100\toff\n";
+        let lines = log.lines().map(|line| io::Result::Ok(line.to_owned()));
+        let result: Result<Vec<_>, _> = DayCollector::new(LogLines::new(lines)).collect();
+        assert_eq!(
+            result.unwrap_err(),
+            "Timestamp in line 4 is earlier than the one in line 3: \
+             \"2019-11-26T08:00+0100\toff\" after \"2019-11-26T09:00+0100\tstart\tA\""
+        );
+    }
+
+    #[test]
+    fn test_open_day_policies() {
+        let log = "2019-11-26T08:00+0100\ton\n\
+                   2019-11-26T08:00+0100\tstart\tA\n\
+                   2019-11-26T12:00+0100\tstop\n\
+                   2019-11-27T09:00+0100\ton\n\
+                   2019-11-27T10:00+0100\tstart\tB\n";
+        let read = |policy: &str| {
+            let lines = log.lines().map(|line| io::Result::Ok(line.to_owned()));
+            let options = ReadOptions {
+                open_days: policy.parse().unwrap(),
+                ..ReadOptions::default()
+            };
+            DayCollector::with_options(LogLines::new(lines), &options)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        assert!(read("error").is_err());
+
+        let days = read("last-entry").unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(
+            days[0].tasks.get_work_duration(),
+            Duration::from_secs(4 * 3600)
+        );
+        assert_eq!(
+            days[0].warnings,
+            vec!["Day 2019-11-26 was not ended with \"off\", closed automatically at 12:00"]
+        );
+        // The log ends on a past day, which is closed as well
+        assert_eq!(days[1].tasks.get_work_duration(), Duration::from_secs(3600));
+        assert_eq!(days[1].warnings.len(), 1);
+
+        let days = read("at:17:30").unwrap();
+        assert_eq!(
+            days[0].tasks.get_work_duration(),
+            Duration::from_secs(9 * 3600 + 1800)
+        );
+        assert_eq!(
+            days[0].warnings,
+            vec!["Day 2019-11-26 was not ended with \"off\", closed automatically at 17:30"]
+        );
+        // The fixed time lies before the last entry of the second day
+        let days = read("at:09:30").unwrap();
+        assert_eq!(days[1].tasks.get_work_duration(), Duration::from_secs(3600));
+
+        let days = read("after:8:00").unwrap();
+        assert_eq!(
+            days[0].tasks.get_work_duration(),
+            Duration::from_secs(8 * 3600)
+        );
+        assert_eq!(
+            days[1].tasks.get_work_duration(),
+            Duration::from_secs(8 * 3600)
+        );
+
+        assert_eq!(
+            "later".parse::<OpenDayPolicy>().unwrap_err(),
+            "unknown policy \"later\", expected error, last-entry, at:HH:MM or after:H:MM"
+        );
+    }
+
+    #[test]
+    fn test_open_day_followed_by_start_or_resume() {
+        let read = |log: &str| {
+            let lines = log.lines().map(|line| io::Result::Ok(line.to_owned()));
+            let options = ReadOptions {
+                open_days: OpenDayPolicy::LastEntry,
+                ..ReadOptions::default()
+            };
+            DayCollector::with_options(LogLines::new(lines), &options)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        let day_1 = "2019-11-26T08:00+0100\ton\n\
+                     2019-11-26T08:00+0100\tstart\tA\n\
+                     2019-11-26T12:00+0100\tstop\n";
+
+        let days = read(&format!(
+            "{}2019-11-27T09:00+0100\tstart\tB\n\
+             2019-11-27T10:00+0100\toff\n",
+            day_1
+        ));
+        assert_eq!(days.len(), 2);
+        assert_eq!(
+            days[0].tasks.get_work_duration(),
+            Duration::from_secs(4 * 3600)
+        );
+        assert_eq!(
+            days[0].warnings,
+            vec!["Day 2019-11-26 was not ended with \"off\", closed automatically at 12:00"]
+        );
+        assert_eq!(
+            days[1].tasks.get_start_time().unwrap(),
+            DateTime::parse_from_rfc3339("2019-11-27T09:00:00+01:00").unwrap()
+        );
+        assert_eq!(days[1].tasks.get_work_duration(), Duration::from_secs(3600));
+        assert_eq!(days[1].tasks.get_tasks()[2], Task::new("B", 60));
+
+        let days = read(&format!(
+            "{}2019-11-27T09:00+0100\tresume\n\
+             2019-11-27T10:00+0100\toff\n",
+            day_1
+        ));
+        assert_eq!(days.len(), 2);
+        assert_eq!(
+            days[0].tasks.get_work_duration(),
+            Duration::from_secs(4 * 3600)
+        );
+        assert_eq!(days[1].tasks.get_work_duration(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_focus() {
+        let days = into_days(DAY_3);
+        let stints = focus::stints(&days[0]);
+        assert_eq!(stints.len(), 18);
+        assert_eq!(
+            Focus::of_day(&stints),
+            Focus {
+                days: 1,
+                stints: 18,
+                switches: 17,
+                task_time: Duration::from_secs(486 * 60),
+                longest: Duration::from_secs(116 * 60),
+                short_time: Duration::from_secs(69 * 60),
+            }
+        );
+
+        let days = into_days(
+            "2019-11-26T08:00+0100\ton\n\
+             2019-11-26T08:00+0100\tstart\tA\n\
+             2019-11-26T08:30+0100\tstart\tB\n\
+             2019-11-26T08:40+0100\trename\tA2\tA\n\
+             2019-11-26T09:00+0100\tstart\tA2\n\
+             2019-11-26T09:00+0100\tstart\tB\n\
+             2019-11-26T10:00+0100\toff\n",
+        );
+        let names: Vec<String> = focus::stints(&days[0])
+            .into_iter()
+            .map(|stint| format!("{} {}", stint.name, stint.duration().as_secs() / 60))
+            .collect();
+        assert_eq!(names, vec!["A2 30", "B 30", "B 60"]);
+    }
+
+    #[test]
+    fn test_breaks() {
+        let log = "2019-11-26T08:00+0100\ton\n\
+                   2019-11-26T12:00+0100\toff\tlunch\n\
+                   2019-11-26T12:45+0100\tstart\tA\n\
+                   2019-11-26T15:00+0100\toff\n\
+                   2019-11-26T15:10+0100\tresume\n\
+                   2019-11-26T16:00+0100\toff\tlunch\n\
+                   2019-11-26T16:15+0100\tresume\n\
+                   2019-11-26T17:00+0100\toff\n";
+        let days = into_days(log);
+        let tasks = &days[0].tasks;
+        assert_eq!(
+            tasks.get_breaks(),
+            &[
+                (Some("lunch".to_owned()), Duration::from_secs(60 * 60)),
+                (None, Duration::from_secs(10 * 60)),
+            ]
+        );
+        assert_eq!(tasks.get_tasks()[0], Task::new("Pause", 70));
+
+        let lines = log.lines().map(|line| io::Result::Ok(line.to_owned()));
+        let options = ReadOptions {
+            pseudo_names: PseudoNames {
+                pause: "Break".to_owned(),
+                undefined: "-".to_owned(),
+            },
+            ..ReadOptions::default()
+        };
+        let days: Vec<_> = DayCollector::with_options(LogLines::new(lines), &options)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let tasks = &days[0].tasks;
+        assert_eq!(
+            tasks.get_tasks(),
+            &[
+                Task::new("Break", 70),
+                Task::new("-", 335),
+                Task::new("A", 135),
+            ]
+        );
+        assert!(tasks.is_pause_task(&tasks.get_tasks()[0]));
+        assert!(tasks.is_undefined_task(&tasks.get_tasks()[1]));
+    }
+
     fn into_days<T: AsRef<str>>(src: T) -> Vec<DayCollection> {
         let lines = src
             .as_ref()
@@ -1,21 +1,274 @@
+use std::env;
+use std::fs;
 use std::io;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::Duration;
 
-use clap::{App, Arg, ArgMatches, SubCommand};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Weekday};
+use clap::{App, AppSettings, Arg, ArgMatches, Shell, SubCommand};
+use regex::Regex;
 
+use timetrack::audit;
+use timetrack::audit::AuditOptions;
 use timetrack::cmd;
-use timetrack::cmd::SummaryScope;
+use timetrack::cmd::{DateRange, SummaryScope};
+use timetrack::fileread::ReadOptions;
+use timetrack::merge;
+use timetrack::print;
+use timetrack::print::{DurationFormat, PrintOptions, TaskSort};
+use timetrack::serve;
+use timetrack::table::TableFormat;
+use timetrack::taskregistry::PseudoNames;
+use timetrack::template::Template;
+use timetrack::timelog::{current_minute, parse_duration, Clock, LogEvent, TimelogEntry};
+use timetrack::timespec::parse_time_spec;
 
 fn main() -> Result<(), String> {
-    let matches = App::new("timetrack")
+    let matches = app().get_matches();
+
+    let mut w = io::stdout();
+    if let ("completions", Some(sub_matches)) = matches.subcommand() {
+        let shell = sub_matches.value_of("shell").unwrap();
+        return completions(&mut w, shell).map_err(|err| err.to_string());
+    }
+
+    if let ("merge", Some(sub_matches)) = matches.subcommand() {
+        let files: Vec<&str> = sub_matches.values_of("files").unwrap().collect();
+        return merge::merge(&mut w, &files);
+    }
+
+    let read = to_read_options(&matches)?;
+    if let ("team", Some(sub_matches)) = matches.subcommand() {
+        let files = matches
+            .values_of("file")
+            .ok_or("No input files given, use --file")?;
+        let members = team_members(files)?;
+        let options = to_print_options(sub_matches)?;
+        return cmd::team(&mut w, &members, &read, &to_range(sub_matches)?, &options);
+    }
+
+    let file_path = match matches.values_of("file") {
+        None => return Err("No input file given, use --file or set TIMETRACK_FILE".to_owned()),
+        Some(files) if files.len() > 1 => {
+            return Err("Only one input file supported for this command".to_owned())
+        }
+        Some(mut files) => files.next().unwrap(),
+    };
+
+    match matches.subcommand() {
+        ("last-active", Some(_)) => cmd::last_active(&mut w, file_path, &read)?,
+        ("summary", Some(sub_matches)) => cmd_summary(&mut w, sub_matches, file_path, &read)?,
+        ("worklog", Some(sub_matches)) => cmd_worklog(&mut w, sub_matches, file_path, &read)?,
+        ("tasks", Some(sub_matches)) => {
+            let options = to_print_options(sub_matches)?;
+            if sub_matches.is_present("watch") {
+                cmd::watch(&mut w, file_path, |w, path| {
+                    cmd::tasks(w, path, &read, &options)
+                })?
+            } else {
+                cmd::tasks(&mut w, file_path, &read, &options)?
+            }
+        }
+        ("status", Some(sub_matches)) => {
+            let options = to_print_options(sub_matches)?;
+            let target = to_target(sub_matches)?;
+            if sub_matches.is_present("watch") {
+                cmd::watch(&mut w, file_path, |w, path| {
+                    cmd::status(w, path, &read, &target, &options)
+                })?
+            } else {
+                cmd::status(&mut w, file_path, &read, &target, &options)?
+            }
+        }
+        ("leave", Some(sub_matches)) => {
+            let options = to_print_options(sub_matches)?;
+            cmd::leave(&mut w, file_path, &read, &to_target(sub_matches)?, &options)?
+        }
+        ("report", Some(sub_matches)) => {
+            let options = to_print_options(sub_matches)?;
+            cmd::report(&mut w, file_path, &read, &to_range(sub_matches)?, &options)?
+        }
+        ("heatmap", Some(sub_matches)) => {
+            let options = to_print_options(sub_matches)?;
+            let year = match sub_matches.value_of("year") {
+                Some(year) => year
+                    .parse()
+                    .ok()
+                    .filter(|&year| print::heatmap_start(year).is_some())
+                    .ok_or_else(|| format!("Invalid year given: {}", year))?,
+                None => read.clock.now().year(),
+            };
+            let target = to_target(sub_matches)?;
+            cmd::heatmap(&mut w, file_path, &read, year, &target, &options)?
+        }
+        ("week", Some(sub_matches)) => {
+            let options = to_print_options(sub_matches)?;
+            let monday = to_week(sub_matches, &read.clock)?;
+            cmd::week(&mut w, file_path, &read, monday, &options)?
+        }
+        ("focus", Some(sub_matches)) => {
+            let options = to_print_options(sub_matches)?;
+            cmd::focus(&mut w, file_path, &read, &to_range(sub_matches)?, &options)?
+        }
+        ("audit", Some(sub_matches)) => {
+            let mut options = AuditOptions {
+                duration_format: to_print_options(sub_matches)?.duration_format,
+                ..AuditOptions::default()
+            };
+            if let Some(duration) = sub_matches.value_of("max-session") {
+                options.max_session = parse_duration(duration)?;
+            }
+            if let Some(duration) = sub_matches.value_of("max-unassigned") {
+                options.max_unassigned = parse_duration(duration)?;
+            }
+            audit::audit(&mut w, file_path, &read, &options)?
+        }
+        ("serve", Some(sub_matches)) => {
+            serve::serve(sub_matches.value_of("bind").unwrap(), file_path, &read)?
+        }
+        ("on", Some(sub_matches)) => cmd::append(
+            file_path,
+            &read,
+            LogEvent::On,
+            &at(sub_matches, &read.clock)?,
+        )?,
+        ("off", Some(sub_matches)) => {
+            let reason = sub_matches
+                .values_of("reason")
+                .map(|words| words.collect::<Vec<_>>().join(" "));
+            cmd::append(
+                file_path,
+                &read,
+                LogEvent::Off(reason),
+                &at(sub_matches, &read.clock)?,
+            )?
+        }
+        ("resume", Some(sub_matches)) => cmd::append(
+            file_path,
+            &read,
+            LogEvent::Resume,
+            &at(sub_matches, &read.clock)?,
+        )?,
+        ("stop", Some(sub_matches)) => cmd::append(
+            file_path,
+            &read,
+            LogEvent::Stop,
+            &at(sub_matches, &read.clock)?,
+        )?,
+        ("start", Some(sub_matches)) => {
+            let name = sub_matches.values_of("task").unwrap().collect::<Vec<_>>();
+            let event = LogEvent::Start(name.join(" "));
+            cmd::append(file_path, &read, event, &at(sub_matches, &read.clock)?)?
+        }
+        ("rename", Some(sub_matches)) => {
+            let to = sub_matches.value_of("to").unwrap().to_owned();
+            let from = sub_matches.value_of("from").map(|s| s.to_owned());
+            cmd::append(
+                file_path,
+                &read,
+                LogEvent::Rename { to, from },
+                &at(sub_matches, &read.clock)?,
+            )?
+        }
+        ("estimate", Some(sub_matches)) => {
+            let name = sub_matches.value_of("task").unwrap().to_owned();
+            let duration = parse_duration(sub_matches.value_of("duration").unwrap())?;
+            let event = LogEvent::Estimate { name, duration };
+            cmd::append(file_path, &read, event, &at(sub_matches, &read.clock)?)?
+        }
+        ("insert", Some(sub_matches)) => {
+            let words: Vec<&str> = sub_matches.values_of("event").unwrap().collect();
+            cmd::insert(
+                file_path,
+                &read,
+                &to_entry(&at(sub_matches, &read.clock)?, &words)?,
+            )?
+        }
+        ("continue", Some(sub_matches)) => {
+            cmd::continue_work(&mut w, file_path, &read, &at(sub_matches, &read.clock)?)?
+        }
+        ("complete-tasks", Some(_)) => cmd::complete_tasks(&mut w, file_path)?,
+        _ => cmd::summaries(
+            &mut w,
+            file_path,
+            &read,
+            SummaryScope::Last(1),
+            &to_print_options(&matches)?,
+        )?,
+    };
+
+    Ok(())
+}
+
+fn app<'a, 'b>() -> App<'a, 'b> {
+    App::new("timetrack")
         .about("Command-line time tracking tool")
         .arg(
             Arg::with_name("file")
                 .short("f")
                 .long("file")
                 .value_name("FILE")
-                .help("Path to input file")
-                .required(true)
-                .takes_value(true),
+                .help("Path to input file; the team report takes several, as LABEL=FILE or a directory")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .env("TIMETRACK_FILE"),
+        )
+        .arg(
+            Arg::with_name("duration-format")
+                .long("duration-format")
+                .value_name("FORMAT")
+                .help("Format of durations: hh:mm (default), decimal, decimal:N digits, or human")
+                .default_value("hh:mm")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Colors tables when writing to a terminal, unless NO_COLOR is set")
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("open-days")
+                .long("open-days")
+                .value_name("POLICY")
+                .help(
+                    "Handling of past days without \"off\": error (default), last-entry, \
+                     at:HH:MM, or after:H:MM of work; auto-closed days are reported as warnings",
+                )
+                .default_value("error")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("pause-name")
+                .long("pause-name")
+                .value_name("NAME")
+                .help("Name of the pseudo-task collecting pauses")
+                .default_value("Pause")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("unassigned-name")
+                .long("unassigned-name")
+                .value_name("NAME")
+                .help("Name of the pseudo-task collecting work time without an active task")
+                .default_value("n/n")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("now")
+                .long("now")
+                .value_name("TIME")
+                .allow_hyphen_values(true)
+                .help(
+                    "Runs as if the current time was the given one, e.g. \"14:00\" or \
+                     \"yesterday 17:45\"; later entries are ignored",
+                )
+                .global(true),
         )
         .subcommand(
             SubCommand::with_name("last-active").about("Displays the last recorded active task"),
@@ -23,6 +276,12 @@ fn main() -> Result<(), String> {
         .subcommand(
             SubCommand::with_name("summary")
                 .about("Displays a task and time summary per work day.")
+                .arg(watch_arg().global(true))
+                .args(&list_args().map(|arg| arg.global(true)))
+                .arg(shares_arg().global(true))
+                .arg(format_arg().global(true))
+                .arg(template_arg().global(true))
+                .arg(target_arg().global(true))
                 .subcommand(
                     SubCommand::with_name("all").about("Displays tasks for all available days"),
                 )
@@ -34,7 +293,10 @@ fn main() -> Result<(), String> {
         )
         .subcommand(
             SubCommand::with_name("worklog")
-                .about("Displays a log of working times per work day, compatible to the input format.")
+                .about(
+                    "Displays a log of working times per work day, compatible to the input format.",
+                )
+                .arg(format_arg().global(true))
                 .subcommand(
                     SubCommand::with_name("all").about("Displays work log for all available days"),
                 )
@@ -44,31 +306,500 @@ fn main() -> Result<(), String> {
                         .arg(Arg::with_name("number").default_value("1")),
                 ),
         )
-        .subcommand(SubCommand::with_name("tasks").about("Displays a list of recorded tasks"))
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name("tasks")
+                .about("Displays a list of recorded tasks")
+                .arg(watch_arg())
+                .args(&list_args())
+                .arg(shares_arg())
+                .arg(template_arg())
+                .arg(target_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Displays the active task and its time spent against its estimate")
+                .arg(watch_arg())
+                .arg(target_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("leave")
+                .about("Displays when today's work reaches the daily target and evens out the week")
+                .arg(target_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Displays task totals over a range of days")
+                .args(&range_args())
+                .args(&list_args())
+                .arg(format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("heatmap")
+                .about("Displays the work time per day of a year as a calendar heatmap")
+                .arg(
+                    Arg::with_name("year")
+                        .long("year")
+                        .value_name("YEAR")
+                        .help("Year to display; defaults to the current year"),
+                )
+                .arg(target_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("week")
+                .about("Displays the days of a week side by side")
+                .arg(
+                    Arg::with_name("week")
+                        .long("week")
+                        .value_name("WEEK")
+                        .help("ISO week to display, as YYYY-Www; defaults to the current week"),
+                )
+                .arg(format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("focus")
+                .about("Displays task switches and uninterrupted work stints per day and week")
+                .args(&range_args())
+                .arg(format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("team")
+                .about("Displays task totals per person and for the whole team")
+                .args(&range_args())
+                .args(&list_args())
+                .arg(format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("merge")
+                .about("Merges log files chronologically and prints the result")
+                .arg(
+                    Arg::with_name("files")
+                        .help("Log files to merge")
+                        .required(true)
+                        .multiple(true)
+                        .min_values(2),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("audit")
+                .about("Lists suspicious entries, like forgotten offs or overly long sessions")
+                .arg(
+                    Arg::with_name("max-session")
+                        .long("max-session")
+                        .value_name("H:MM")
+                        .help("Longest work session without a pause; defaults to 10:00"),
+                )
+                .arg(
+                    Arg::with_name("max-unassigned")
+                        .long("max-unassigned")
+                        .value_name("H:MM")
+                        .help("Most work time per day not assigned to a task; defaults to 1:00"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serves read-only JSON reports over HTTP")
+                .arg(
+                    Arg::with_name("bind")
+                        .long("bind")
+                        .value_name("ADDRESS")
+                        .help("Address and port to listen on")
+                        .default_value("127.0.0.1:8080"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("on")
+                .about("Starts a new work day")
+                .arg(at_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("off")
+                .about("Stops working and starts a pause")
+                .arg(at_arg())
+                .arg(
+                    Arg::with_name("reason")
+                        .help("Reason for the break, e.g. \"lunch\"")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("resume")
+                .about("Resumes working without an active task")
+                .arg(at_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("start")
+                .about("Starts working on a task")
+                .arg(at_arg())
+                .arg(
+                    Arg::with_name("task")
+                        .help("Task name")
+                        .required(true)
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stop")
+                .about("Stops working on the active task")
+                .arg(at_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("rename")
+                .about("Renames the active task or the given task")
+                .arg(at_arg())
+                .arg(Arg::with_name("to").help("New task name").required(true))
+                .arg(Arg::with_name("from").help("Task to rename instead of the active one")),
+        )
+        .subcommand(
+            SubCommand::with_name("continue")
+                .about("Continues the last active task, or resumes work if there is none")
+                .arg(at_arg())
+                .arg(
+                    Arg::with_name("time")
+                        .help("Same as --at")
+                        .conflicts_with("at")
+                        .hidden(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("estimate")
+                .about("Records the estimated effort for a task")
+                .arg(at_arg())
+                .arg(Arg::with_name("task").help("Task name").required(true))
+                .arg(
+                    Arg::with_name("duration")
+                        .help("Estimated duration, as HH:MM")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("insert")
+                .about("Inserts a forgotten entry at its place in a past day")
+                .arg(at_arg().required(true))
+                .arg(
+                    Arg::with_name("event")
+                        .help("Event as in the log file, e.g. \"start Task name\", \"off\" or \"on\"")
+                        .required(true)
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Generates a shell completion script")
+                .arg(
+                    Arg::with_name("shell")
+                        .required(true)
+                        .possible_values(&["bash", "zsh", "fish"]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("complete-tasks")
+                .setting(AppSettings::Hidden)
+                .about("Lists known task names, most recently used first"),
+        )
+}
 
-    let file_path = matches.value_of("file").unwrap();
+fn cmd_summary(
+    mut w: impl io::Write,
+    matches: &ArgMatches,
+    file_path: &str,
+    read: &ReadOptions,
+) -> Result<(), String> {
+    let scope = to_scope(matches)?;
+    let options = to_print_options(matches)?;
+    if is_present(matches, "watch") {
+        cmd::watch(&mut w, file_path, |w, path| {
+            cmd::summaries(w, path, read, scope, &options)
+        })
+    } else {
+        cmd::summaries(&mut w, file_path, read, scope, &options)
+    }
+}
 
-    let mut w = io::stdout();
+fn cmd_worklog(
+    mut w: impl io::Write,
+    matches: &ArgMatches,
+    file_path: &str,
+    read: &ReadOptions,
+) -> Result<(), String> {
+    let scope = to_scope(matches)?;
+    let options = to_print_options(matches)?;
+    cmd::worklog(&mut w, file_path, read, scope, &options)
+}
+
+fn range_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+    [
+        Arg::with_name("from")
+            .long("from")
+            .value_name("DATE")
+            .help("First day to include, as YYYY-MM-DD"),
+        Arg::with_name("to")
+            .long("to")
+            .value_name("DATE")
+            .help("Last day to include, as YYYY-MM-DD"),
+    ]
+}
+
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .possible_values(&["text", "markdown", "html"])
+        .help("Output format; markdown and html produce documents with headings and tables")
+}
+
+fn template_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("template")
+        .long("template")
+        .value_name("TEMPLATE")
+        .help(
+            "Custom output layout, given inline or as @FILE. Placeholders: {date}, {work_time}, \
+             {start}, {end}, {intervals}, and {tasks}...{end_tasks} repeating {n}, {name}, {time} \
+             and {active} per task. Inline templates may use \\n and \\t.",
+        )
+}
+
+fn at_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("at")
+        .long("at")
+        .value_name("TIME")
+        .allow_hyphen_values(true)
+        .help(
+            "Time of the entry instead of now: HH:MM, an offset like -15m or -1h30m, \
+             or a day and time like \"yesterday 17:45\", \"monday 09:00\" or \"2019-11-28 09:30\"",
+        )
+}
+
+fn target_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("target")
+        .long("target")
+        .value_name("H:MM")
+        .help("Daily work time target")
+        .default_value("8:00")
+}
+
+fn watch_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("watch")
+        .long("watch")
+        .help("Redraws the output whenever the input file changes")
+}
+
+fn list_args<'a, 'b>() -> [Arg<'a, 'b>; 3] {
+    [
+        Arg::with_name("sort")
+            .long("sort")
+            .value_name("ORDER")
+            .possible_values(&["first-start", "duration", "name"])
+            .help("Order of listed tasks"),
+        Arg::with_name("filter")
+            .long("filter")
+            .value_name("REGEX")
+            .help("Lists only tasks with a name matching the regular expression"),
+        Arg::with_name("hide-pseudo")
+            .long("hide-pseudo")
+            .help("Hides the pause and unassigned work time pseudo-tasks"),
+    ]
+}
+
+fn shares_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("shares")
+        .long("shares")
+        .help("Shows each task's share of the work time and of the elapsed day")
+}
+
+/// Tells whether the argument was given, either directly or to a subcommand for global arguments.
+fn is_present(matches: &ArgMatches, name: &str) -> bool {
     match matches.subcommand() {
-        ("last-active", Some(_)) => cmd::last_active(&mut w, file_path)?,
-        ("summary", Some(sub_matches)) => cmd_summary(&mut w, sub_matches, file_path)?,
-        ("worklog", Some(sub_matches)) => cmd_worklog(&mut w, sub_matches, file_path)?,
-        ("tasks", Some(_)) => cmd::tasks(&mut w, file_path)?,
-        _ => cmd::summaries(&mut w, file_path, SummaryScope::Last(1))?,
+        (_, Some(sub_matches)) => matches.is_present(name) || sub_matches.is_present(name),
+        _ => matches.is_present(name),
+    }
+}
+
+/// Returns the argument value, either given directly or to a subcommand for global arguments.
+fn value_of<'a>(matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    match matches.subcommand() {
+        (_, Some(sub_matches)) => sub_matches.value_of(name).or(matches.value_of(name)),
+        _ => matches.value_of(name),
+    }
+}
+
+fn to_print_options(matches: &ArgMatches) -> Result<PrintOptions, String> {
+    let sort = match value_of(matches, "sort") {
+        Some("duration") => TaskSort::Duration,
+        Some("name") => TaskSort::Name,
+        _ => TaskSort::FirstStart,
     };
+    let filter = value_of(matches, "filter")
+        .map(|filter| Regex::new(filter).map_err(|e| format!("Invalid filter given: {}", e)))
+        .transpose()?;
 
-    Ok(())
+    Ok(PrintOptions {
+        sort,
+        filter,
+        hide_pseudo: is_present(matches, "hide-pseudo"),
+        shares: is_present(matches, "shares"),
+        format: table_format(matches),
+        template: value_of(matches, "template").map(to_template).transpose()?,
+        duration_format: value_of(matches, "duration-format")
+            .unwrap_or("hh:mm")
+            .parse::<DurationFormat>()
+            .map_err(|err| format!("Invalid duration format given: {}", err))?,
+        target: value_of(matches, "target")
+            .map(parse_duration)
+            .transpose()
+            .map_err(|err| format!("Invalid target given: {}", err))?,
+    })
 }
 
-fn cmd_summary(mut w: impl io::Write, matches: &ArgMatches, file_path: &str) -> Result<(), String> {
-    let scope = to_scope(matches)?;
-    cmd::summaries(&mut w, file_path, scope)
+fn to_read_options(matches: &ArgMatches) -> Result<ReadOptions, String> {
+    Ok(ReadOptions {
+        open_days: value_of(matches, "open-days")
+            .unwrap_or("error")
+            .parse()
+            .map_err(|err| format!("Invalid open day policy given: {}", err))?,
+        clock: match value_of(matches, "now") {
+            Some(spec) => Clock::Fixed(parse_time_spec(spec, &current_minute())?),
+            None => Clock::System,
+        },
+        pseudo_names: PseudoNames {
+            pause: value_of(matches, "pause-name")
+                .unwrap_or("Pause")
+                .to_owned(),
+            undefined: value_of(matches, "unassigned-name")
+                .unwrap_or("n/n")
+                .to_owned(),
+        },
+    })
 }
 
-fn cmd_worklog(mut w: impl io::Write, matches: &ArgMatches, file_path: &str) -> Result<(), String> {
-    let scope = to_scope(matches)?;
-    cmd::worklog(&mut w, file_path, scope)
+/// Aligns tables written to a terminal, and colors them unless disabled. Output to pipes and
+/// files keeps the tab-separated format.
+fn table_format(matches: &ArgMatches) -> TableFormat {
+    match value_of(matches, "format") {
+        Some("markdown") => return TableFormat::Markdown,
+        Some("html") => return TableFormat::Html,
+        _ => {}
+    }
+
+    let terminal = io::stdout().is_terminal();
+    match value_of(matches, "color") {
+        Some("always") => TableFormat::Colored,
+        Some("never") if terminal => TableFormat::Aligned,
+        Some("never") => TableFormat::Plain,
+        _ if terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) => {
+            TableFormat::Colored
+        }
+        _ if terminal => TableFormat::Aligned,
+        _ => TableFormat::Plain,
+    }
+}
+
+fn to_target(matches: &ArgMatches) -> Result<Duration, String> {
+    parse_duration(matches.value_of("target").unwrap_or("8:00"))
+        .map_err(|err| format!("Invalid target given: {}", err))
+}
+
+fn to_template(value: &str) -> Result<Template, String> {
+    let text = match value.strip_prefix('@') {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|err| format!("Could not read template file {:?}: {}", path, err))?,
+        None => value.replace("\\n", "\n").replace("\\t", "\t"),
+    };
+    text.parse()
+        .map_err(|err| format!("Invalid template given: {}", err))
+}
+
+/// Builds an entry from an event given as words on the command line. The words of a task name
+/// or break reason are joined with spaces, other words are fields as in the log file.
+fn to_entry(time: &DateTime<FixedOffset>, words: &[&str]) -> Result<TimelogEntry, String> {
+    let fields = match words {
+        [event @ ("start" | "off"), name @ ..] if !name.is_empty() => {
+            format!("{}\t{}", event, name.join(" "))
+        }
+        _ => words.join("\t"),
+    };
+    let line = format!("{}\t{}", time.format("%Y-%m-%dT%H:%M%z"), fields);
+    TimelogEntry::parse_from_str(&line).map_err(|err| format!("Invalid event given: {}", err))
+}
+
+/// Returns the time given with `--at`, or the current time.
+fn at(matches: &ArgMatches, clock: &Clock) -> Result<DateTime<FixedOffset>, String> {
+    let now = clock.now();
+    match matches.value_of("at").or(matches.value_of("time")) {
+        Some(spec) => parse_time_spec(spec, &now),
+        None => Ok(now),
+    }
+}
+
+fn to_range(matches: &ArgMatches) -> Result<DateRange, String> {
+    let parse = |name| {
+        matches
+            .value_of(name)
+            .map(|date| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|e| format!("Invalid date given: {}", e))
+            })
+            .transpose()
+    };
+    Ok(DateRange {
+        from: parse("from")?,
+        to: parse("to")?,
+    })
+}
+
+/// Returns the Monday of the week given with `--week`, or of the current week.
+fn to_week(matches: &ArgMatches, clock: &Clock) -> Result<NaiveDate, String> {
+    let (year, week) = match matches.value_of("week") {
+        Some(spec) => spec
+            .split_once("-W")
+            .and_then(|(year, week)| Some((year.parse().ok()?, week.parse().ok()?)))
+            .ok_or_else(|| format!("Invalid week given: {:?}, expected e.g. 2019-W48", spec))?,
+        None => {
+            let week = clock.now().date().naive_local().iso_week();
+            (week.year(), week.week())
+        }
+    };
+    NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+        .ok_or_else(|| format!("Invalid week given: {}-W{:02}", year, week))
+}
+
+/// Resolves the team's input files, given as `LABEL=FILE` or as plain file names labeled by
+/// their stem. Directories are expanded to the files they contain.
+fn team_members<'a>(files: impl Iterator<Item = &'a str>) -> Result<Vec<(String, String)>, String> {
+    let mut members = Vec::new();
+    for file in files {
+        match file.split_once('=') {
+            Some((label, path)) if !label.is_empty() => {
+                members.push((label.to_owned(), path.to_owned()))
+            }
+            _ if Path::new(file).is_dir() => {
+                let entries = fs::read_dir(file)
+                    .map_err(|err| format!("Could not read directory {:?}: {}", file, err))?;
+                let mut paths = Vec::new();
+                for entry in entries {
+                    let path = entry.map_err(|err| err.to_string())?.path();
+                    let hidden = path
+                        .file_name()
+                        .is_none_or(|name| name.to_string_lossy().starts_with('.'));
+                    if path.is_file() && !hidden {
+                        paths.push(path);
+                    }
+                }
+                paths.sort();
+                for path in paths {
+                    members.push((file_label(&path), path.to_string_lossy().into_owned()));
+                }
+            }
+            _ => members.push((file_label(Path::new(file)), file.to_owned())),
+        }
+    }
+
+    Ok(members)
+}
+
+fn file_label(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(|| path.to_string_lossy(), |stem| stem.to_string_lossy())
+        .into_owned()
 }
 
 fn to_scope(matches: &ArgMatches) -> Result<SummaryScope, String> {
@@ -86,3 +817,106 @@ fn to_scope(matches: &ArgMatches) -> Result<SummaryScope, String> {
 
     Ok(scope)
 }
+
+/// Generates the completion script for the given shell, extended by completion of task names via
+/// the hidden `complete-tasks` subcommand. Task names are read from the file given with `--file`
+/// on the command line being completed, or else from the one in `TIMETRACK_FILE`.
+fn completions(mut w: impl io::Write, shell: &str) -> io::Result<()> {
+    let shell = match shell {
+        "bash" => Shell::Bash,
+        "zsh" => Shell::Zsh,
+        _ => Shell::Fish,
+    };
+    let mut buf = Vec::new();
+    app().gen_completions_to("timetrack", shell, &mut buf);
+    let script = String::from_utf8_lossy(&buf);
+
+    let script = match shell {
+        Shell::Bash => script.replace(
+            "complete -F _timetrack ",
+            &format!(
+                "{}\ncomplete -F _timetrack_with_tasks ",
+                BASH_TASK_COMPLETION
+            ),
+        ),
+        Shell::Zsh => script
+            .replace("Task name:_files", "Task name:_timetrack_tasks")
+            .replace(
+                "\n_timetrack \"$@\"",
+                &format!("\n{}\n_timetrack \"$@\"", ZSH_TASK_COMPLETION),
+            ),
+        _ => format!("{}\n{}", script, FISH_TASK_COMPLETION),
+    };
+
+    w.write_all(script.as_bytes())
+}
+
+const BASH_TASK_COMPLETION: &str = r#"_timetrack_with_tasks() {
+    local i
+    local -a file_args=()
+    for (( i=1; i < COMP_CWORD; i++ )); do
+        case "${COMP_WORDS[i]}" in
+            -f|--file)
+                # "--file=FILE" is split at the equals sign
+                [[ "${COMP_WORDS[i+1]}" == "=" ]] && (( i++ ))
+                (( i++ ))
+                file_args=( --file "${COMP_WORDS[i]/#\~/$HOME}" )
+                ;;
+            --file=*)
+                file_args=( --file "${COMP_WORDS[i]#--file=}" )
+                ;;
+            -f?*)
+                file_args=( --file "${COMP_WORDS[i]#-f}" )
+                ;;
+            -*)
+                ;;
+            start|estimate)
+                local IFS=$'\n'
+                local task
+                COMPREPLY=()
+                for task in $(compgen -W "$(timetrack "${file_args[@]}" complete-tasks 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"); do
+                    COMPREPLY+=( "$(printf '%q' "${task}")" )
+                done
+                return 0
+                ;;
+        esac
+    done
+    _timetrack "$@"
+}
+"#;
+
+const ZSH_TASK_COMPLETION: &str = r#"(( $+functions[_timetrack_tasks] )) ||
+_timetrack_tasks() {
+    local -a tasks line_words
+    local i file
+    line_words=( ${(z)BUFFER} )
+    for (( i = 1; i < $#line_words; i++ )); do
+        case $line_words[i] in
+            (-f|--file) file=$line_words[i+1] ;;
+            (--file=*) file=${line_words[i]#--file=} ;;
+            (-f?*) file=${line_words[i]#-f} ;;
+        esac
+    done
+    file=${${(Q)file}/#\~/$HOME}
+    tasks=("${(@f)$(_call_program tasks timetrack ${file:+--file ${(q)file}} complete-tasks 2>/dev/null)}")
+    compadd -V tasks -a tasks
+}
+"#;
+
+const FISH_TASK_COMPLETION: &str = r#"function __timetrack_tasks
+    set -l tokens (commandline -opc)
+    set -l file_args
+    for i in (seq (count $tokens))
+        switch $tokens[$i]
+            case -f --file
+                set -q tokens[(math $i + 1)]; and set file_args --file $tokens[(math $i + 1)]
+            case '--file=*'
+                set file_args --file (string replace -- --file= '' $tokens[$i])
+            case '-f*'
+                set file_args --file (string sub -s 3 -- $tokens[$i])
+        end
+    end
+    timetrack $file_args complete-tasks 2>/dev/null
+end
+complete -c timetrack -n "__fish_seen_subcommand_from start estimate" -f -k -a "(__timetrack_tasks)"
+"#;
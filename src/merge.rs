@@ -0,0 +1,157 @@
+use std::io;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::fileread::{read_log_lines, DayCollector, LogLine, ReadOptions};
+use crate::taskregistry::{OpenDayPolicy, TaskRegistryBuilder};
+use crate::timelog::TimelogEntry;
+
+/// An entry along with the comment and blank lines preceding it. The last entry of a file also
+/// carries the lines following it.
+struct Block {
+    ignored: Vec<String>,
+    entry: TimelogEntry,
+    trailing: Vec<String>,
+    source: usize,
+    line_nr: usize,
+}
+
+struct WorkTime {
+    on: DateTime<FixedOffset>,
+    off: DateTime<FixedOffset>,
+    source: usize,
+}
+
+/// Merges the entries of several log files chronologically and writes the result. Entries
+/// present in more than one file are written once. Nothing is written if the files contain
+/// overlapping work times, or if the merged entries are not a valid log.
+pub fn merge(mut w: impl io::Write, paths: &[&str]) -> Result<(), String> {
+    let mut blocks = Vec::new();
+    let mut work_times = Vec::new();
+    for (source, path) in paths.iter().enumerate() {
+        blocks.extend(read_blocks(path, source)?);
+        work_times.extend(read_work_times(path, source)?);
+    }
+
+    let mut conflicts = find_overlaps(&work_times, paths);
+
+    blocks.sort_by_key(|block| block.entry.time);
+    let mut merged: Vec<Block> = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let duplicate = merged
+            .iter_mut()
+            .rev()
+            .take_while(|other| other.entry.time == block.entry.time)
+            .find(|other| other.entry == block.entry);
+        match duplicate {
+            Some(other) => {
+                if other.trailing != block.trailing {
+                    other.trailing.extend(block.trailing);
+                }
+            }
+            None => merged.push(block),
+        }
+    }
+
+    if conflicts.is_empty() {
+        let mut builder = TaskRegistryBuilder::new();
+        for block in &merged {
+            if let Err(err) = builder.add_entry(&block.entry) {
+                conflicts.push(format!(
+                    "Conflicting entry \"{}\" in {} line {}: {}",
+                    block.entry, paths[block.source], block.line_nr, err
+                ));
+                break;
+            }
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(format!("Could not merge files:\n{}", conflicts.join("\n")));
+    }
+
+    for block in &merged {
+        for line in &block.ignored {
+            writeln!(&mut w, "{}", line).map_err(|err| err.to_string())?;
+        }
+        writeln!(&mut w, "{}", block.entry).map_err(|err| err.to_string())?;
+        for line in &block.trailing {
+            writeln!(&mut w, "{}", line).map_err(|err| err.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read_blocks(path: &str, source: usize) -> Result<Vec<Block>, String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+
+    let mut blocks = Vec::new();
+    let mut ignored = Vec::new();
+    for (line_nr, line) in lines {
+        match line.map_err(|err| format!("{} (in {} line {})", err, path, line_nr))? {
+            LogLine::Ignored(line) => ignored.push(line),
+            LogLine::Entry(entry) => blocks.push(Block {
+                ignored: std::mem::take(&mut ignored),
+                entry,
+                trailing: Vec::new(),
+                source,
+                line_nr,
+            }),
+        }
+    }
+    if let Some(last) = blocks.last_mut() {
+        last.trailing = ignored;
+    }
+
+    Ok(blocks)
+}
+
+/// Reads the work times of a file. A day left open ends at its last entry rather than now, as
+/// the times of several files are compared.
+fn read_work_times(path: &str, source: usize) -> Result<Vec<WorkTime>, String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    let options = ReadOptions {
+        open_days: OpenDayPolicy::LastEntry,
+        ..ReadOptions::default()
+    };
+
+    let mut work_times = Vec::new();
+    for day in DayCollector::with_options(lines, &options) {
+        let day = day.map_err(|err| format!("{} (in {})", err, path))?;
+        for (on, off) in day.tasks.get_work_times() {
+            work_times.push(WorkTime {
+                on: *on,
+                off: *off,
+                source,
+            });
+        }
+    }
+
+    Ok(work_times)
+}
+
+/// Lists work times of different files that overlap. Work times recorded identically in several
+/// files are not considered a conflict.
+fn find_overlaps(work_times: &[WorkTime], paths: &[&str]) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    for (i, a) in work_times.iter().enumerate() {
+        for b in &work_times[i + 1..] {
+            let identical = a.on == b.on && a.off == b.off;
+            if a.source != b.source && !identical && a.on < b.off && b.on < a.off {
+                conflicts.push(format!(
+                    "Overlapping work times: {} – {} in {} and {} – {} in {}",
+                    a.on.format("%F %R"),
+                    a.off.format("%R"),
+                    paths[a.source],
+                    b.on.format("%F %R"),
+                    b.off.format("%R"),
+                    paths[b.source]
+                ));
+            }
+        }
+    }
+    conflicts
+}
@@ -1,68 +1,683 @@
+use std::collections::HashMap;
 use std::io;
 use std::ops::Sub;
+use std::str::FromStr;
 use std::time::Duration;
 
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate};
+use regex::Regex;
 
-use crate::taskregistry::TaskRegistry;
+use crate::focus;
+use crate::focus::Focus;
+use crate::projection::{Leave, Projection};
+use crate::table::{escape_html, paint, RowStyle, Table, TableFormat};
+use crate::taskregistry::{Task, TaskRegistry, TaskTotal, TaskTotals};
+use crate::template::Template;
 
-pub fn tasks(mut w: impl io::Write, registry: &TaskRegistry) -> io::Result<()> {
-    let tasks = registry.get_tasks();
+const SHARE_BAR_WIDTH: usize = 20;
 
-    writeln!(&mut w, "#\ttime\ttask name")?;
-    for (n, task) in tasks.iter().enumerate().skip(1) {
-        writeln!(&mut w, "{}\t{}", n, task)?;
+const WEEK_TOP_TASKS: usize = 3;
+const WEEK_TASK_NAME_WIDTH: usize = 20;
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+th { background: #f0f0f0; }
+tr.highlight { font-weight: bold; color: #176617; }
+tr.dim { color: #888; }
+tr.alert { color: #c00; }";
+
+/// How durations are written.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum DurationFormat {
+    /// Hours and minutes, as in `07:45`.
+    HoursMinutes,
+    /// Decimal hours with the given number of fractional digits, as in `7.75`.
+    Decimal(usize),
+    /// Hours and minutes with units, as in `7h45m`.
+    Human,
+}
+
+impl DurationFormat {
+    pub fn format(&self, duration: &Duration) -> String {
+        let mins = duration.as_secs() / 60;
+        let m = mins % 60;
+        let h = mins / 60;
+        match self {
+            DurationFormat::HoursMinutes => format!("{:02}:{:02}", h, m),
+            DurationFormat::Decimal(precision) => {
+                format!("{:.*}", *precision, mins as f64 / 60.0)
+            }
+            DurationFormat::Human if h == 0 => format!("{}m", m),
+            DurationFormat::Human if m == 0 => format!("{}h", h),
+            DurationFormat::Human => format!("{}h{:02}m", h, m),
+        }
+    }
+}
+
+impl FromStr for DurationFormat {
+    type Err = String;
+
+    /// Parses `hh:mm`, `human`, `decimal`, or `decimal:N` for N fractional digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hh:mm" => Ok(DurationFormat::HoursMinutes),
+            "human" => Ok(DurationFormat::Human),
+            "decimal" => Ok(DurationFormat::Decimal(2)),
+            _ => match s.strip_prefix("decimal:").map(str::parse) {
+                Some(Ok(precision)) if precision <= 6 => Ok(DurationFormat::Decimal(precision)),
+                Some(_) => Err(format!("invalid precision in {:?}, expected 0 to 6", s)),
+                None => Err(format!(
+                    "unknown format {:?}, expected hh:mm, decimal, decimal:N or human",
+                    s
+                )),
+            },
+        }
     }
-    writeln!(
-        &mut w,
-        "\t{}\ttotal work time",
-        format_duration(&registry.get_work_duration())
-    )?;
-    Ok(())
 }
 
-pub fn day_summary(mut w: impl io::Write, registry: &TaskRegistry) -> io::Result<()> {
-    writeln!(&mut w, "=== {:?}", registry.get_start_time().unwrap().date().naive_utc())?;
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum TaskSort {
+    FirstStart,
+    Duration,
+    Name,
+}
 
-    let tasks = registry.get_tasks();
+/// Options controlling which tasks are listed, in which order, and how.
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    pub sort: TaskSort,
+    pub filter: Option<Regex>,
+    pub hide_pseudo: bool,
+    pub shares: bool,
+    pub format: TableFormat,
+    pub template: Option<Template>,
+    pub duration_format: DurationFormat,
+    /// Daily work time target; a day's work time beyond it is marked as overtime.
+    pub target: Option<Duration>,
+}
 
-    for (n, task) in tasks.iter().enumerate() {
-        match n {
-            0 => writeln!(&mut w, "\t{}", task)?,
-            _ => writeln!(&mut w, "{}\t{}", n, task)?,
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            sort: TaskSort::FirstStart,
+            filter: None,
+            hide_pseudo: false,
+            shares: false,
+            format: TableFormat::Plain,
+            template: None,
+            duration_format: DurationFormat::HoursMinutes,
+            target: None,
         }
     }
+}
 
-    writeln!(
-        &mut w,
-        "-- Work time: {}",
-        format_duration(&registry.get_work_duration())
-    )?;
+impl PrintOptions {
+    /// Returns the style of a day's work time: an alert if it exceeds the target.
+    fn work_time_style(&self, work_time: &Duration) -> RowStyle {
+        match self.target {
+            Some(target) if *work_time > target => RowStyle::Alert,
+            _ => RowStyle::Normal,
+        }
+    }
+
+    /// Filters and sorts the given tasks. Each task is returned with its original position, so
+    /// that task numbers stay the same regardless of the options.
+    pub(crate) fn select<'a, T: Listed>(
+        &self,
+        tasks: &'a [T],
+        is_pseudo: impl Fn(&T) -> bool,
+    ) -> Vec<(usize, &'a T)> {
+        let mut selected: Vec<(usize, &T)> = tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| !(self.hide_pseudo && is_pseudo(task)))
+            .filter(|(_, task)| {
+                self.filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.is_match(task.name()))
+            })
+            .collect();
 
-    writeln!(&mut w, "-- Work hours:")?;
-    writeln!(&mut w, "on   \toff  \ttime \tpause")?;
+        match self.sort {
+            TaskSort::FirstStart => {}
+            TaskSort::Duration => {
+                selected.sort_by_key(|(_, task)| std::cmp::Reverse(task.duration()))
+            }
+            TaskSort::Name => {
+                selected.sort_by_key(|(_, task)| task.name().to_lowercase());
+            }
+        }
+
+        selected
+    }
+}
+
+pub(crate) trait Listed {
+    fn name(&self) -> &str;
+    fn duration(&self) -> Duration;
+}
+
+impl Listed for Task {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+impl Listed for TaskTotal {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+pub fn tasks(
+    mut w: impl io::Write,
+    registry: &TaskRegistry,
+    totals: &TaskTotals,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    let tasks = options.select(registry.get_tasks(), |task| registry.is_pseudo_task(task));
+
+    // The pause task at the start of the day is left out of the list
+    let listed: Vec<(usize, &Task)> = tasks.iter().filter(|(n, _)| *n > 0).cloned().collect();
+    if let Some(template) = &options.template {
+        return template.render(w, registry, &listed, options);
+    }
+
+    let mut table = task_table(registry, &listed, options, true);
+
+    let work_duration = registry.get_work_duration();
+    let mut total_row = vec![
+        String::new(),
+        options.duration_format.format(&work_duration),
+    ];
+    if options.shares {
+        let day_share = share(&work_duration, &elapsed_duration(registry));
+        total_row.extend([String::new(), format_percent(day_share), String::new()]);
+    }
+    total_row.push("total work time".to_owned());
+    table.add_styled_row(total_row, options.work_time_style(&work_duration));
+    table.write(&mut w, options.format)?;
+
+    estimates(&mut w, &tasks, totals, options)?;
+    Ok(())
+}
+
+pub fn day_summary(
+    mut w: impl io::Write,
+    registry: &TaskRegistry,
+    totals: &TaskTotals,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    let tasks = options.select(registry.get_tasks(), |task| registry.is_pseudo_task(task));
+    if let Some(template) = &options.template {
+        return template.render(w, registry, &tasks, options);
+    }
+
+    let date = registry.get_start_time().unwrap().date().naive_utc();
+    heading(&mut w, &format!("{:?}", date), options.format)?;
+
+    task_table(registry, &tasks, options, options.format.is_document())
+        .write(&mut w, options.format)?;
+
+    let work_duration = registry.get_work_duration();
+    let work_time = paint(
+        &options.duration_format.format(&work_duration),
+        options.work_time_style(&work_duration),
+        options.format,
+    );
+    note(&mut w, &format!("Work time: {}", work_time), options.format)?;
+
+    section(&mut w, "Work hours", options.format)?;
+    let mut work_hours = Table::with_header(&["on   ", "off  ", "time ", "pause"]);
     let mut last_off: Option<DateTime<FixedOffset>> = None;
     for (on, off) in registry.get_work_times() {
-        let delta = format_duration(&off.sub(*on).to_std().unwrap());
+        let delta = options
+            .duration_format
+            .format(&off.sub(*on).to_std().unwrap());
         let pause = match last_off {
-            Some(last_off) => format_duration(&on.sub(last_off).to_std().unwrap()),
+            Some(last_off) => options
+                .duration_format
+                .format(&on.sub(last_off).to_std().unwrap()),
             None => "".to_string(),
         };
         last_off = Some(*off);
-        writeln!(
-            &mut w,
-            "{}\t{}\t{}\t{}",
-            on.format("%H:%M"),
-            off.format("%H:%M"),
+        work_hours.add_row(vec![
+            on.format("%H:%M").to_string(),
+            off.format("%H:%M").to_string(),
             delta,
-            pause
-        )?;
+            pause,
+        ]);
+    }
+    work_hours.write(&mut w, options.format)?;
+
+    breaks(&mut w, registry, options)?;
+    estimates(&mut w, &tasks, totals, options)?;
+
+    Ok(())
+}
+
+/// Writes the break time per reason, if any break was given a reason.
+fn breaks(
+    mut w: impl io::Write,
+    registry: &TaskRegistry,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    let breaks = registry.get_breaks();
+    if breaks.iter().all(|(reason, _)| reason.is_none()) {
+        return Ok(());
+    }
+
+    section(&mut w, "Breaks", options.format)?;
+    let mut table = Table::with_header(&["time ", "reason"]);
+    for (reason, duration) in breaks {
+        let (reason, style) = match reason {
+            Some(reason) => (reason.clone(), RowStyle::Normal),
+            None => ("no reason given".to_owned(), RowStyle::Dim),
+        };
+        table.add_styled_row(
+            vec![options.duration_format.format(duration), reason],
+            style,
+        );
+    }
+    table.write(&mut w, options.format)
+}
+
+pub fn status(
+    mut w: impl io::Write,
+    registry: &TaskRegistry,
+    totals: &TaskTotals,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    writeln!(
+        &mut w,
+        "=== {:?}",
+        registry.get_start_time().unwrap().date().naive_utc()
+    )?;
+
+    match registry.get_active() {
+        Some(task) if registry.is_undefined_task(task) => writeln!(&mut w, "No active task")?,
+        Some(task) => {
+            writeln!(&mut w, "Active task: {}", task.name)?;
+            let total = totals.get(&task.name);
+            writeln!(
+                &mut w,
+                "Task time: {} today, {} total",
+                options.duration_format.format(&task.duration),
+                options
+                    .duration_format
+                    .format(&total.map_or(task.duration, |t| t.duration))
+            )?;
+            if let Some(total) = total {
+                if let Some(estimate) = total.estimate {
+                    writeln!(
+                        &mut w,
+                        "Estimate: {}, {}",
+                        options.duration_format.format(&estimate),
+                        format_remaining(&total.duration, &estimate, options)
+                    )?;
+                }
+            }
+        }
+        None => writeln!(&mut w, "Off work")?,
     }
 
+    let work_duration = registry.get_work_duration();
+    writeln!(
+        &mut w,
+        "Work time: {}",
+        paint(
+            &options.duration_format.format(&work_duration),
+            options.work_time_style(&work_duration),
+            options.format,
+        )
+    )?;
+
     Ok(())
 }
 
-pub fn worklog(mut w: impl io::Write, registry: &TaskRegistry) -> io::Result<()> {
+/// Writes when the running day reaches the daily target and when the week balance reaches zero.
+pub fn projection(
+    mut w: impl io::Write,
+    projection: &Projection,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    writeln!(
+        &mut w,
+        "Target {} {}",
+        options.duration_format.format(&projection.target.work),
+        format_leave(&projection.target, "reached", &projection.date, options)
+    )?;
+    let (sign, balance) = match projection.week_balance.to_std() {
+        Ok(balance) => ('+', balance),
+        Err(_) => ('-', (-projection.week_balance).to_std().unwrap_or_default()),
+    };
+    writeln!(
+        &mut w,
+        "Week balance {}{} {}",
+        sign,
+        options.duration_format.format(&balance),
+        format_leave(
+            &projection.balanced,
+            "evened out",
+            &projection.date,
+            options
+        )
+    )
+}
+
+/// Describes when a work time is reached, with the date if it is not on the projected day.
+fn format_leave(leave: &Leave, verb: &str, date: &NaiveDate, options: &PrintOptions) -> String {
+    let time = match leave.time {
+        None => return verb.to_owned(),
+        Some(time) if time.date().naive_local() == *date => time.format("%R"),
+        Some(time) => time.format("%F %R"),
+    };
+    if leave.missing_break > Duration::from_secs(0) {
+        format!(
+            "{} at {}, including {} more break",
+            verb,
+            time,
+            options.duration_format.format(&leave.missing_break)
+        )
+    } else {
+        format!("{} at {}", verb, time)
+    }
+}
+
+pub fn task_totals(
+    mut w: impl io::Write,
+    heading: &str,
+    totals: &TaskTotals,
+    work_duration: &Duration,
+    days: usize,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    self::heading(&mut w, heading, options.format)?;
+    let mut table = Table::with_header(&["#", "time", "task name"]);
+    for (n, task) in options.select(totals.get_tasks(), |task| task.pseudo) {
+        let style = if task.pseudo {
+            RowStyle::Dim
+        } else {
+            RowStyle::Normal
+        };
+        table.add_styled_row(
+            vec![
+                (n + 1).to_string(),
+                options.duration_format.format(&task.duration),
+                task.name.clone(),
+            ],
+            style,
+        );
+    }
+    table.add_row(vec![
+        String::new(),
+        options.duration_format.format(work_duration),
+        format!(
+            "total work time in {} {}",
+            days,
+            if days == 1 { "day" } else { "days" }
+        ),
+    ]);
+    table.write(&mut w, options.format)
+}
+
+/// Writes the days of a week side by side, each with its work times, total work time and the
+/// tasks it was spent on most. Days without work are left empty.
+pub fn week(
+    mut w: impl io::Write,
+    heading: &str,
+    days: &[(NaiveDate, Option<TaskRegistry>)],
+    options: &PrintOptions,
+) -> io::Result<()> {
+    self::heading(&mut w, heading, options.format)?;
+
+    let mut header = vec![String::new()];
+    header.extend(
+        days.iter()
+            .map(|(date, _)| date.format("%a %m-%d").to_string()),
+    );
+    let mut table = Table::with_header(&header);
+
+    let cells = |label: &str, cell: &dyn Fn(&TaskRegistry) -> Option<String>| {
+        let mut row = vec![label.to_owned()];
+        row.extend(
+            days.iter()
+                .map(|(_, registry)| registry.as_ref().and_then(cell).unwrap_or_default()),
+        );
+        row
+    };
+
+    let intervals = days
+        .iter()
+        .filter_map(|(_, registry)| registry.as_ref())
+        .map(|registry| registry.get_work_times().len())
+        .max()
+        .unwrap_or(0);
+    for i in 0..intervals {
+        let label = if i == 0 { "times" } else { "" };
+        table.add_row(cells(label, &|registry| {
+            registry
+                .get_work_times()
+                .get(i)
+                .map(|(on, off)| format!("{}-{}", on.format("%R"), off.format("%R")))
+        }));
+    }
+    table.add_styled_row(
+        cells("total", &|registry| {
+            Some(
+                options
+                    .duration_format
+                    .format(&registry.get_work_duration()),
+            )
+        }),
+        RowStyle::Highlight,
+    );
+    for i in 0..WEEK_TOP_TASKS {
+        let label = if i == 0 { "tasks" } else { "" };
+        table.add_row(cells(label, &|registry| {
+            let mut tasks: Vec<&Task> = registry
+                .get_tasks()
+                .iter()
+                .filter(|task| !registry.is_pseudo_task(task))
+                .collect();
+            tasks.sort_by_key(|task| std::cmp::Reverse(task.duration));
+            tasks.get(i).map(|task| {
+                format!(
+                    "{} {}",
+                    options.duration_format.format(&task.duration),
+                    shorten(&task.name, WEEK_TASK_NAME_WIDTH)
+                )
+            })
+        }));
+    }
+
+    table.write(&mut w, options.format)
+}
+
+/// Cuts the text to the given number of characters, marking the cut with an ellipsis.
+fn shorten(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_owned();
+    }
+    let mut shortened: String = text.chars().take(width - 1).collect();
+    shortened.push('…');
+    shortened
+}
+
+/// Returns the Monday the heatmap of the given year starts with, or `None` if the weeks of the
+/// year cannot be represented.
+pub fn heatmap_start(year: i32) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, 1, 1)?;
+    NaiveDate::from_ymd_opt(year.checked_add(1)?, 1, 7)?;
+    first.checked_sub_signed(chrono::Duration::days(
+        first.weekday().num_days_from_monday() as i64,
+    ))
+}
+
+/// Writes a calendar of the year with one column per week, each day shaded by its work time
+/// relative to the target. Work days without work between the first and the last day of the
+/// log are marked as absent.
+pub fn heatmap(
+    mut w: impl io::Write,
+    year: i32,
+    work: &HashMap<NaiveDate, Duration>,
+    logged: Option<(NaiveDate, NaiveDate)>,
+    target: &Duration,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    let first_monday = heatmap_start(year).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid year given: {}", year),
+        )
+    })?;
+    heading(&mut w, &year.to_string(), options.format)?;
+
+    let weeks: Vec<NaiveDate> = first_monday
+        .iter_weeks()
+        .take_while(|monday| monday.year() <= year)
+        .collect();
+
+    // Months are labeled above the week of their first day
+    let mut months = String::new();
+    for (i, monday) in weeks.iter().enumerate() {
+        let first_of_month = monday
+            .iter_days()
+            .take(7)
+            .find(|date| date.day() == 1 && date.year() == year);
+        let width = months.chars().count();
+        if let Some(date) = first_of_month.filter(|_| width <= i) {
+            months.push_str(&" ".repeat(i - width));
+            months.push_str(&date.format("%b").to_string());
+        }
+    }
+    writeln!(&mut w, "    {}", months.trim_end())?;
+
+    let third = *target / 3;
+    let (mut worked, mut over, mut absent) = (0, 0, 0);
+    for weekday in 0..7 {
+        let day = first_monday + chrono::Duration::days(weekday);
+        let mut line = format!("{:<4}", day.format("%a"));
+        for monday in &weeks {
+            let date = *monday + chrono::Duration::days(weekday);
+            let in_log = logged.is_some_and(|(from, to)| from <= date && date <= to);
+            let cell = match work.get(&date) {
+                _ if date.year() != year => " ".to_owned(),
+                Some(duration) if duration > target => {
+                    worked += 1;
+                    over += 1;
+                    paint("█", RowStyle::Alert, options.format)
+                }
+                Some(duration) => {
+                    worked += 1;
+                    let shade = if *duration < third {
+                        "░"
+                    } else if *duration < third * 2 {
+                        "▒"
+                    } else {
+                        "▓"
+                    };
+                    paint(shade, RowStyle::Highlight, options.format)
+                }
+                None if in_log && weekday < 5 => {
+                    absent += 1;
+                    paint("·", RowStyle::Dim, options.format)
+                }
+                None => " ".to_owned(),
+            };
+            line.push_str(&cell);
+        }
+        writeln!(&mut w, "{}", line.trim_end())?;
+    }
+
+    writeln!(
+        &mut w,
+        "-- · absent, ░ under {}, ▒ under {}, ▓ up to {}, █ over target",
+        options.duration_format.format(&third),
+        options.duration_format.format(&(third * 2)),
+        options.duration_format.format(target)
+    )?;
+    writeln!(
+        &mut w,
+        "-- {} days worked, {} over target, {} absent",
+        worked, over, absent
+    )
+}
+
+/// Writes focus metrics per day, followed by their trend per week.
+pub fn focus(
+    mut w: impl io::Write,
+    heading: &str,
+    days: &[(NaiveDate, Focus)],
+    options: &PrintOptions,
+) -> io::Result<()> {
+    self::heading(&mut w, heading, options.format)?;
+    let short_title = format!(
+        "under {}",
+        options.duration_format.format(&focus::SHORT_STINT)
+    );
+
+    section(&mut w, "Days", options.format)?;
+    let mut table = Table::with_header(&["date", "switches", "longest", "average", &short_title]);
+    for (date, focus) in days {
+        table.add_row(vec![
+            date.to_string(),
+            focus.switches.to_string(),
+            options.duration_format.format(&focus.longest),
+            options.duration_format.format(&focus.average_stint()),
+            format_short_time(focus, options),
+        ]);
+    }
+    table.write(&mut w, options.format)?;
+
+    section(&mut w, "Weeks", options.format)?;
+    let mut table = Table::with_header(&[
+        "week",
+        "days",
+        "switches/day",
+        "longest",
+        "average",
+        &short_title,
+    ]);
+    for (week, focus) in focus::by_week(days) {
+        table.add_row(vec![
+            format!("{}-W{:02}", week.year(), week.week()),
+            focus.days.to_string(),
+            format!("{:.1}", focus.switches_per_day()),
+            options.duration_format.format(&focus.longest),
+            options.duration_format.format(&focus.average_stint()),
+            format_short_time(&focus, options),
+        ]);
+    }
+    table.write(&mut w, options.format)
+}
+
+fn format_short_time(focus: &Focus, options: &PrintOptions) -> String {
+    format!(
+        "{} ({}%)",
+        options.duration_format.format(&focus.short_time),
+        focus.short_share()
+    )
+}
+
+pub fn worklog(
+    mut w: impl io::Write,
+    registry: &TaskRegistry,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    if options.format.is_document() {
+        return worklog_table(w, registry, options);
+    }
+
     let mut first = true;
     for (on, off) in registry.get_work_times() {
         let on_label;
@@ -80,10 +695,222 @@ pub fn worklog(mut w: impl io::Write, registry: &TaskRegistry) -> io::Result<()>
     Ok(())
 }
 
-fn format_duration(work_time: &Duration) -> String {
-    let secs = work_time.as_secs();
-    let mins = secs / 60;
-    let m = mins % 60;
-    let h = mins / 60;
-    format!("{:02}:{:02}", h, m)
+/// Writes the work times of the day as a table, for document formats where the log format
+/// makes no sense.
+fn worklog_table(
+    mut w: impl io::Write,
+    registry: &TaskRegistry,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    if let Ok(start) = registry.get_start_time() {
+        heading(
+            &mut w,
+            &start.format("%A, %B %e").to_string(),
+            options.format,
+        )?;
+    }
+
+    let mut table = Table::with_header(&["on", "off", "time"]);
+    for (on, off) in registry.get_work_times() {
+        table.add_row(vec![
+            on.format("%H:%M").to_string(),
+            off.format("%H:%M").to_string(),
+            options
+                .duration_format
+                .format(&off.sub(*on).to_std().unwrap()),
+        ]);
+    }
+    table.write(&mut w, options.format)
+}
+
+/// Starts the output of a command. HTML output is a self-contained document with inline styles,
+/// so that it can be opened from a file.
+pub fn document_start(mut w: impl io::Write, title: &str, format: TableFormat) -> io::Result<()> {
+    match format {
+        TableFormat::Html => writeln!(
+            &mut w,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>",
+            escape_html(title),
+            HTML_STYLE
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Ends the output of a command started with `document_start`.
+pub fn document_end(mut w: impl io::Write, format: TableFormat) -> io::Result<()> {
+    match format {
+        TableFormat::Html => writeln!(&mut w, "</body>\n</html>"),
+        _ => Ok(()),
+    }
+}
+
+fn heading(mut w: impl io::Write, text: &str, format: TableFormat) -> io::Result<()> {
+    match format {
+        TableFormat::Markdown => writeln!(&mut w, "## {}\n", text),
+        TableFormat::Html => writeln!(&mut w, "<h2>{}</h2>", escape_html(text)),
+        _ => writeln!(&mut w, "=== {}", text),
+    }
+}
+
+fn section(mut w: impl io::Write, title: &str, format: TableFormat) -> io::Result<()> {
+    match format {
+        TableFormat::Markdown => writeln!(&mut w, "### {}\n", title),
+        TableFormat::Html => writeln!(&mut w, "<h3>{}</h3>", escape_html(title)),
+        _ => writeln!(&mut w, "-- {}:", title),
+    }
+}
+
+fn note(mut w: impl io::Write, text: &str, format: TableFormat) -> io::Result<()> {
+    match format {
+        TableFormat::Markdown => writeln!(&mut w, "{}\n", text),
+        TableFormat::Html => writeln!(&mut w, "<p>{}</p>", escape_html(text)),
+        _ => writeln!(&mut w, "-- {}", text),
+    }
+}
+
+/// Builds the table of the given tasks, with the first task of the day left unnumbered.
+fn task_table(
+    registry: &TaskRegistry,
+    tasks: &[(usize, &Task)],
+    options: &PrintOptions,
+    with_header: bool,
+) -> Table {
+    let mut table = if with_header {
+        let mut header = vec!["#", "time"];
+        if options.shares {
+            header.extend(["work", "day", "share"]);
+        }
+        header.push("task name");
+        Table::with_header(&header)
+    } else {
+        Table::new()
+    };
+
+    let work_duration = registry.get_work_duration();
+    let elapsed_duration = elapsed_duration(registry);
+    for (n, task) in tasks {
+        let number = if *n == 0 {
+            String::new()
+        } else {
+            n.to_string()
+        };
+        let active = if task.active { "*" } else { "" };
+        let mut cells = vec![
+            number,
+            format!(
+                "{}{}",
+                options.duration_format.format(&task.duration),
+                active
+            ),
+        ];
+        if options.shares {
+            let work_share = Some(share(&task.duration, &work_duration))
+                .filter(|_| !registry.is_pause_task(task));
+            cells.push(work_share.map_or_else(String::new, format_percent));
+            cells.push(format_percent(share(&task.duration, &elapsed_duration)));
+            cells.push(work_share.map_or_else(String::new, format_bar));
+        }
+        cells.push(task.name.clone());
+        let style = if task.active {
+            RowStyle::Highlight
+        } else if registry.is_pause_task(task) {
+            RowStyle::Dim
+        } else {
+            RowStyle::Normal
+        };
+        table.add_styled_row(cells, style);
+    }
+
+    table
+}
+
+/// Returns the time from the start of the first to the end of the last work period of the day.
+fn elapsed_duration(registry: &TaskRegistry) -> Duration {
+    let work_times = registry.get_work_times();
+    match (work_times.first(), work_times.last()) {
+        (Some((start, _)), Some((_, end))) => end.sub(*start).to_std().unwrap_or_default(),
+        _ => Duration::from_secs(0),
+    }
+}
+
+fn share(part: &Duration, total: &Duration) -> f64 {
+    if total.as_secs() == 0 {
+        0.0
+    } else {
+        part.as_secs_f64() / total.as_secs_f64()
+    }
+}
+
+fn format_percent(share: f64) -> String {
+    format!("{:.0}%", share * 100.0)
+}
+
+/// Renders a share as a horizontal bar, using partial block glyphs for eighths of a character.
+fn format_bar(share: f64) -> String {
+    const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+    let eighths = (share.clamp(0.0, 1.0) * (SHARE_BAR_WIDTH * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    match eighths % 8 {
+        0 => {}
+        rest => bar.push(PARTIAL_BLOCKS[rest - 1]),
+    }
+    bar
+}
+
+fn estimates(
+    mut w: impl io::Write,
+    tasks: &[(usize, &Task)],
+    totals: &TaskTotals,
+    options: &PrintOptions,
+) -> io::Result<()> {
+    let estimated: Vec<_> = tasks
+        .iter()
+        .filter_map(|(_, task)| totals.get(&task.name))
+        .filter_map(|total| total.estimate.map(|estimate| (total, estimate)))
+        .collect();
+    if estimated.is_empty() {
+        return Ok(());
+    }
+
+    section(&mut w, "Estimates", options.format)?;
+    let mut table = Table::with_header(&["spent", "estimate", "remaining", "task name"]);
+    for (total, estimate) in estimated {
+        let (remaining, style) = if total.duration > estimate {
+            let overrun = options.duration_format.format(&(total.duration - estimate));
+            (format!("-{}", overrun), RowStyle::Alert)
+        } else {
+            (
+                options.duration_format.format(&(estimate - total.duration)),
+                RowStyle::Normal,
+            )
+        };
+        table.add_styled_row(
+            vec![
+                options.duration_format.format(&total.duration),
+                options.duration_format.format(&estimate),
+                remaining,
+                total.name.clone(),
+            ],
+            style,
+        );
+    }
+
+    table.write(&mut w, options.format)
+}
+
+fn format_remaining(spent: &Duration, estimate: &Duration, options: &PrintOptions) -> String {
+    if spent > estimate {
+        format!(
+            "{} over estimate",
+            options.duration_format.format(&(*spent - *estimate))
+        )
+    } else {
+        format!(
+            "{} remaining",
+            options.duration_format.format(&(*estimate - *spent))
+        )
+    }
 }
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::taskregistry::TaskRegistry;
+
+/// Breaks required by law, by work time: more than nine hours of work need 45 minutes of break,
+/// more than six hours need 30 minutes.
+const REQUIRED_BREAKS: [(Duration, Duration); 2] = [
+    (Duration::from_secs(9 * 3600), Duration::from_secs(45 * 60)),
+    (Duration::from_secs(6 * 3600), Duration::from_secs(30 * 60)),
+];
+
+/// The point in time at which the running day reaches a work time.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Leave {
+    /// The work time to reach.
+    pub work: Duration,
+    /// The time it is reached, or `None` if it already is.
+    pub time: Option<DateTime<FixedOffset>>,
+    /// Break time still to be taken until then, included in `time`.
+    pub missing_break: Duration,
+}
+
+/// When the running day may end, with regard to the daily target and to the week.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Projection {
+    /// The day projected.
+    pub date: NaiveDate,
+    pub target: Leave,
+    /// Work time minus target over the previous work days of the week.
+    pub week_balance: chrono::Duration,
+    /// When the week balance including today reaches zero.
+    pub balanced: Leave,
+}
+
+/// Returns the break a day with the given work time requires.
+pub fn required_break(work: &Duration) -> Duration {
+    REQUIRED_BREAKS
+        .iter()
+        .find(|(limit, _)| work > limit)
+        .map_or(Duration::from_secs(0), |(_, duration)| *duration)
+}
+
+/// Sums up work time minus target of the given days.
+pub fn balance(work_durations: &[Duration], target: &Duration) -> chrono::Duration {
+    work_durations
+        .iter()
+        .map(|work| to_signed(work) - to_signed(target))
+        .fold(chrono::Duration::zero(), |sum, balance| sum + balance)
+}
+
+/// Projects when the running day reaches the daily target and evens out the week balance,
+/// assuming work continues from now on and missing breaks are taken.
+pub fn project(
+    registry: &TaskRegistry,
+    week_balance: chrono::Duration,
+    target: &Duration,
+    now: &DateTime<FixedOffset>,
+) -> Projection {
+    let balanced_work = (to_signed(target) - week_balance)
+        .to_std()
+        .unwrap_or_default();
+    Projection {
+        date: now.date().naive_local(),
+        target: leave_at(registry, target, now),
+        week_balance,
+        balanced: leave_at(registry, &balanced_work, now),
+    }
+}
+
+fn leave_at(registry: &TaskRegistry, work: &Duration, now: &DateTime<FixedOffset>) -> Leave {
+    let done = registry.get_work_duration();
+    if done >= *work {
+        return Leave {
+            work: *work,
+            time: None,
+            missing_break: Duration::from_secs(0),
+        };
+    }
+
+    let elapsed = registry
+        .get_start_time()
+        .ok()
+        .and_then(|start| (*now - start).to_std().ok())
+        .unwrap_or_default();
+    let taken_break = elapsed.saturating_sub(done);
+    let missing_break = required_break(work).saturating_sub(taken_break);
+    Leave {
+        work: *work,
+        time: Some(*now + to_signed(&(*work - done + missing_break))),
+        missing_break,
+    }
+}
+
+fn to_signed(duration: &Duration) -> chrono::Duration {
+    chrono::Duration::from_std(*duration).unwrap_or_else(|_| chrono::Duration::max_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::taskregistry::TaskRegistryBuilder;
+    use crate::timelog::{Clock, TimelogEntry};
+
+    fn running_day(log: &str, now: &str) -> (TaskRegistry, DateTime<FixedOffset>) {
+        let now = DateTime::parse_from_rfc3339(now).unwrap();
+        let mut builder = TaskRegistryBuilder::with_options(
+            Default::default(),
+            Clock::Fixed(now),
+            Default::default(),
+        );
+        for line in log.lines() {
+            let entry = TimelogEntry::parse_from_str(line).unwrap();
+            builder.add_entry(&entry).unwrap();
+        }
+        (builder.finish(), now)
+    }
+
+    #[test]
+    fn test_required_break() {
+        let hours = |h: u64| Duration::from_secs(h * 3600);
+        assert_eq!(required_break(&hours(6)), Duration::from_secs(0));
+        assert_eq!(required_break(&hours(8)), Duration::from_secs(30 * 60));
+        assert_eq!(required_break(&hours(10)), Duration::from_secs(45 * 60));
+    }
+
+    #[test]
+    fn test_project() {
+        let (registry, now) = running_day(
+            "2019-11-26T08:00+0100\ton\n\
+             2019-11-26T12:00+0100\toff\n\
+             2019-11-26T12:20+0100\tresume",
+            "2019-11-26T13:00:00+01:00",
+        );
+        let time = |s| Some(DateTime::parse_from_rfc3339(s).unwrap());
+
+        // 3:20 of work left, plus 10 minutes of the required break
+        let projection = project(
+            &registry,
+            chrono::Duration::minutes(-45),
+            &Duration::from_secs(8 * 3600),
+            &now,
+        );
+        assert_eq!(projection.target.time, time("2019-11-26T16:30:00+01:00"));
+        assert_eq!(projection.target.missing_break, Duration::from_secs(600));
+        assert_eq!(
+            projection.balanced.work,
+            Duration::from_secs(8 * 3600 + 2700)
+        );
+        assert_eq!(projection.balanced.time, time("2019-11-26T17:15:00+01:00"));
+
+        // No break required for less than six hours
+        let projection = project(
+            &registry,
+            chrono::Duration::hours(3),
+            &Duration::from_secs(8 * 3600),
+            &now,
+        );
+        assert_eq!(projection.balanced.time, time("2019-11-26T13:20:00+01:00"));
+        assert_eq!(projection.balanced.missing_break, Duration::from_secs(0));
+
+        let projection = project(
+            &registry,
+            chrono::Duration::hours(8),
+            &Duration::from_secs(8 * 3600),
+            &now,
+        );
+        assert_eq!(projection.balanced.time, None);
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt::Write as _;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::fileread::{read_log_lines, DayCollection, DayCollector, ReadOptions};
+use crate::taskregistry::{TaskRegistry, TaskTotals};
+
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Response {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            body: format!("{{\"error\":{}}}", json_string(message)),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Serves read-only JSON views of the given log file over HTTP until the process is terminated.
+/// The file is read anew for every request.
+pub fn serve(bind: &str, path: &str, read: &ReadOptions) -> Result<(), String> {
+    let listener =
+        TcpListener::bind(bind).map_err(|err| format!("Could not bind to {}: {}", bind, err))?;
+
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| handle_connection(stream, path, read));
+        if let Err(err) = result {
+            eprintln!("Error while handling request: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, path: &str, read: &ReadOptions) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => handle_request(target, path, read),
+        (Some(_), Some(_)) => Response::error(405, "Only GET requests are supported"),
+        _ => Response::error(400, "Malformed request"),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+/// Answers a GET request for the given request target with data read from the file at `path`.
+pub fn handle_request(target: &str, path: &str, read: &ReadOptions) -> Response {
+    let target = target.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = target.split('/').filter(|s| !s.is_empty()).collect();
+
+    let days = match read_days(path, read) {
+        Ok(days) => days,
+        Err(err) => return Response::error(500, &err),
+    };
+
+    match segments.as_slice() {
+        ["days"] => {
+            let items: Vec<String> = days.iter().map(|day| day_overview(&day.tasks)).collect();
+            Response::ok(json_array(items))
+        }
+        ["days", date] => {
+            let date = match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(date) => date,
+                Err(_) => return Response::error(400, "Expected date in format YYYY-MM-DD"),
+            };
+            match days.iter().find(|day| day_date(&day.tasks) == Some(date)) {
+                Some(day) => Response::ok(day_details(&day.tasks)),
+                None => Response::error(404, "No entries for this date"),
+            }
+        }
+        ["tasks"] => {
+            let totals = task_totals(&days);
+            let items = totals
+                .get_tasks()
+                .iter()
+                .map(|task| {
+                    format!(
+                        "{{\"name\":{},\"minutes\":{},\"estimate_minutes\":{}}}",
+                        json_string(&task.name),
+                        minutes(&task.duration),
+                        json_minutes(task.estimate.as_ref())
+                    )
+                })
+                .collect();
+            Response::ok(json_array(items))
+        }
+        ["status"] => match days.last() {
+            Some(day) => Response::ok(status(&day.tasks, &task_totals(&days))),
+            None => Response::error(404, "No entries recorded"),
+        },
+        _ => Response::error(404, "Unknown resource"),
+    }
+}
+
+fn read_days(path: &str, read: &ReadOptions) -> Result<Vec<DayCollection>, String> {
+    let lines =
+        read_log_lines(path).map_err(|err| format!("Could not read file {:?}: {}", path, err))?;
+    DayCollector::with_options(lines, read).collect()
+}
+
+fn task_totals(days: &[DayCollection]) -> TaskTotals {
+    let mut totals = TaskTotals::new();
+    for day in days {
+        totals.add(&day.tasks);
+    }
+    totals
+}
+
+fn day_date(registry: &TaskRegistry) -> Option<NaiveDate> {
+    registry
+        .get_start_time()
+        .ok()
+        .map(|time| time.date().naive_local())
+}
+
+fn day_overview(registry: &TaskRegistry) -> String {
+    format!(
+        "{{\"date\":{},\"work_minutes\":{}}}",
+        json_date(registry),
+        minutes(&registry.get_work_duration())
+    )
+}
+
+fn day_details(registry: &TaskRegistry) -> String {
+    let work_times = registry
+        .get_work_times()
+        .iter()
+        .map(|(on, off)| format!("{{\"on\":{},\"off\":{}}}", json_time(on), json_time(off)))
+        .collect();
+    let tasks = registry
+        .get_tasks()
+        .iter()
+        .map(|task| {
+            format!(
+                "{{\"name\":{},\"minutes\":{},\"active\":{}}}",
+                json_string(&task.name),
+                minutes(&task.duration),
+                task.active
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"date\":{},\"work_minutes\":{},\"work_times\":{},\"tasks\":{}}}",
+        json_date(registry),
+        minutes(&registry.get_work_duration()),
+        json_array(work_times),
+        json_array(tasks)
+    )
+}
+
+fn status(registry: &TaskRegistry, totals: &TaskTotals) -> String {
+    let active = registry
+        .get_active()
+        .filter(|task| !registry.is_undefined_task(task));
+    let total = active.and_then(|task| totals.get(&task.name));
+
+    format!(
+        "{{\"date\":{},\"working\":{},\"active_task\":{},\"task_minutes\":{},\"task_total_minutes\":{},\"estimate_minutes\":{},\"work_minutes\":{}}}",
+        json_date(registry),
+        registry.get_active().is_some(),
+        active.map_or("null".to_owned(), |task| json_string(&task.name)),
+        json_minutes(active.map(|task| &task.duration)),
+        json_minutes(total.map(|total| &total.duration)),
+        json_minutes(total.and_then(|total| total.estimate.as_ref())),
+        minutes(&registry.get_work_duration())
+    )
+}
+
+fn minutes(duration: &Duration) -> u64 {
+    duration.as_secs() / 60
+}
+
+fn json_minutes(duration: Option<&Duration>) -> String {
+    duration.map_or("null".to_owned(), |d| minutes(d).to_string())
+}
+
+fn json_date(registry: &TaskRegistry) -> String {
+    day_date(registry).map_or("null".to_owned(), |date| {
+        json_string(&date.format("%Y-%m-%d").to_string())
+    })
+}
+
+fn json_time(time: &DateTime<FixedOffset>) -> String {
+    json_string(&time.to_rfc3339())
+}
+
+fn json_array(items: Vec<String>) -> String {
+    format!("[{}]", items.join(","))
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
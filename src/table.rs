@@ -0,0 +1,300 @@
+use std::io;
+
+const COLUMN_GAP: &str = "  ";
+
+const STYLE_RESET: &str = "\x1b[0m";
+const STYLE_HEADER: &str = "\x1b[1m";
+const STYLE_HIGHLIGHT: &str = "\x1b[1;32m";
+const STYLE_DIM: &str = "\x1b[2m";
+const STYLE_ALERT: &str = "\x1b[31m";
+
+/// How tables are written.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum TableFormat {
+    /// Cells separated by tabs, suitable for further processing.
+    Plain,
+    /// Cells padded to the width of their column.
+    Aligned,
+    /// Aligned, with rows colored according to their style.
+    Colored,
+    /// Markdown table, with a header row.
+    Markdown,
+    /// HTML table, with rows classed according to their style.
+    Html,
+}
+
+impl TableFormat {
+    /// Tells whether the output is a document format, in which tables need a header row.
+    pub fn is_document(&self) -> bool {
+        matches!(self, TableFormat::Markdown | TableFormat::Html)
+    }
+}
+
+/// Emphasis of a table row, only visible in colored tables.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum RowStyle {
+    #[default]
+    Normal,
+    Highlight,
+    Dim,
+    Alert,
+}
+
+/// Rows of text cells, with an optional header row.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    header: Option<Vec<String>>,
+    rows: Vec<(Vec<String>, RowStyle)>,
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    pub fn with_header<T: ToString>(header: &[T]) -> Table {
+        Table {
+            header: Some(header.iter().map(|title| title.to_string()).collect()),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, cells: Vec<String>) {
+        self.add_styled_row(cells, RowStyle::Normal);
+    }
+
+    pub fn add_styled_row(&mut self, cells: Vec<String>, style: RowStyle) {
+        self.rows.push((cells, style));
+    }
+
+    pub fn write(&self, w: impl io::Write, format: TableFormat) -> io::Result<()> {
+        match format {
+            TableFormat::Plain => self.write_plain(w),
+            TableFormat::Aligned => self.write_aligned(w, false),
+            TableFormat::Colored => self.write_aligned(w, true),
+            TableFormat::Markdown => self.write_markdown(w),
+            TableFormat::Html => self.write_html(w),
+        }
+    }
+
+    /// Writes the table with cells separated by tabs, one row per line.
+    pub fn write_plain(&self, mut w: impl io::Write) -> io::Result<()> {
+        for row in self.all_rows().map(|(row, _)| row) {
+            writeln!(&mut w, "{}", row.join("\t"))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the table with every column padded to its widest cell. The last cell of a row is
+    /// not padded, so long task names do not widen the table.
+    fn write_aligned(&self, mut w: impl io::Write, color: bool) -> io::Result<()> {
+        let mut widths: Vec<usize> = Vec::new();
+        for (row, _) in self.all_rows() {
+            for (i, cell) in row.iter().enumerate() {
+                let width = cell.chars().count();
+                match widths.get_mut(i) {
+                    Some(max) => *max = (*max).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+
+        for (row, style) in self.all_rows() {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:1$}", cell, width))
+                .collect::<Vec<_>>()
+                .join(COLUMN_GAP);
+            let line = line.trim_end();
+            match style.filter(|_| color) {
+                Some(style) => writeln!(&mut w, "{}{}{}", style, line, STYLE_RESET)?,
+                None => writeln!(&mut w, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the table in Markdown, followed by a blank line. A table without header gets an
+    /// empty one, as Markdown requires it.
+    fn write_markdown(&self, mut w: impl io::Write) -> io::Result<()> {
+        let columns = self.all_rows().map(|(row, _)| row.len()).max().unwrap_or(0);
+        let empty_header = vec![String::new(); columns];
+        let header = self.header.as_ref().unwrap_or(&empty_header);
+
+        let write_row = |w: &mut dyn io::Write, row: &[String]| {
+            let cells: Vec<String> = (0..columns)
+                .map(|i| {
+                    row.get(i)
+                        .map_or("", |cell| cell.trim())
+                        .replace('|', "\\|")
+                })
+                .collect();
+            writeln!(w, "| {} |", cells.join(" | "))
+        };
+        write_row(&mut w, header)?;
+        writeln!(&mut w, "|{}", "---|".repeat(columns))?;
+        for (row, _) in &self.rows {
+            write_row(&mut w, row)?;
+        }
+        writeln!(&mut w)
+    }
+
+    /// Writes the table as an HTML element.
+    fn write_html(&self, mut w: impl io::Write) -> io::Result<()> {
+        writeln!(&mut w, "<table>")?;
+        if let Some(header) = &self.header {
+            let cells: String = header
+                .iter()
+                .map(|cell| format!("<th>{}</th>", escape_html(cell.trim())))
+                .collect();
+            writeln!(&mut w, "<thead><tr>{}</tr></thead>", cells)?;
+        }
+        writeln!(&mut w, "<tbody>")?;
+        for (row, style) in &self.rows {
+            let class = match style {
+                RowStyle::Normal => "",
+                RowStyle::Highlight => " class=\"highlight\"",
+                RowStyle::Dim => " class=\"dim\"",
+                RowStyle::Alert => " class=\"alert\"",
+            };
+            let cells: String = row
+                .iter()
+                .map(|cell| format!("<td>{}</td>", escape_html(cell)))
+                .collect();
+            writeln!(&mut w, "<tr{}>{}</tr>", class, cells)?;
+        }
+        writeln!(&mut w, "</tbody>")?;
+        writeln!(&mut w, "</table>")
+    }
+
+    /// Iterates over the header and data rows, along with the escape sequence of their style.
+    fn all_rows(&self) -> impl Iterator<Item = (&Vec<String>, Option<&'static str>)> {
+        let header = self.header.iter().map(|row| (row, Some(STYLE_HEADER)));
+        let rows = self
+            .rows
+            .iter()
+            .map(|(row, style)| (row, escape_sequence(*style)));
+        header.chain(rows)
+    }
+}
+
+fn escape_sequence(style: RowStyle) -> Option<&'static str> {
+    match style {
+        RowStyle::Normal => None,
+        RowStyle::Highlight => Some(STYLE_HIGHLIGHT),
+        RowStyle::Dim => Some(STYLE_DIM),
+        RowStyle::Alert => Some(STYLE_ALERT),
+    }
+}
+
+/// Returns the text in the given style for colored output, or unchanged otherwise.
+pub fn paint(text: &str, style: RowStyle, format: TableFormat) -> String {
+    match escape_sequence(style).filter(|_| format == TableFormat::Colored) {
+        Some(sequence) => format!("{}{}{}", sequence, text, STYLE_RESET),
+        None => text.to_owned(),
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Table {
+        let mut table = Table::with_header(&["#", "time", "task name"]);
+        table.add_row(vec![
+            "1".into(),
+            "01:30".into(),
+            "CHORE - instable tests".into(),
+        ]);
+        table.add_styled_row(
+            vec!["12".into(), "00:05*".into(), "Mails".into()],
+            RowStyle::Highlight,
+        );
+        table.add_row(vec!["".into(), "01:35".into(), "total work time".into()]);
+        table
+    }
+
+    fn render(table: &Table, format: TableFormat) -> String {
+        let mut w: Vec<u8> = Vec::new();
+        table.write(&mut w, format).unwrap();
+        String::from_utf8(w).unwrap()
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(
+            render(&sample(), TableFormat::Plain),
+            "#\ttime\ttask name\n\
+             1\t01:30\tCHORE - instable tests\n\
+             12\t00:05*\tMails\n\
+             \t01:35\ttotal work time\n"
+        );
+    }
+
+    #[test]
+    fn test_aligned() {
+        assert_eq!(
+            render(&sample(), TableFormat::Aligned),
+            "#   time    task name\n\
+             1   01:30   CHORE - instable tests\n\
+             12  00:05*  Mails\n    \
+             01:35   total work time\n"
+        );
+    }
+
+    #[test]
+    fn test_colored() {
+        assert_eq!(
+            render(&sample(), TableFormat::Colored),
+            "\x1b[1m#   time    task name\x1b[0m\n\
+             1   01:30   CHORE - instable tests\n\
+             \x1b[1;32m12  00:05*  Mails\x1b[0m\n    \
+             01:35   total work time\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown() {
+        let mut table = sample();
+        table.add_row(vec!["".into(), "".into(), "a | b".into()]);
+        assert_eq!(
+            render(&table, TableFormat::Markdown),
+            "| # | time | task name |\n\
+             |---|---|---|\n\
+             | 1 | 01:30 | CHORE - instable tests |\n\
+             | 12 | 00:05* | Mails |\n\
+             |  | 01:35 | total work time |\n\
+             |  |  | a \\| b |\n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let mut table = Table::new();
+        table.add_styled_row(vec!["<b>".into(), "R&D".into()], RowStyle::Dim);
+        assert_eq!(
+            render(&table, TableFormat::Html),
+            "<table>\n\
+             <tbody>\n\
+             <tr class=\"dim\"><td>&lt;b&gt;</td><td>R&amp;D</td></tr>\n\
+             </tbody>\n\
+             </table>\n"
+        );
+    }
+}
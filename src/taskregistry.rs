@@ -1,17 +1,29 @@
 use std::collections::HashMap;
-use std::fmt;
-use std::fmt::{Error, Formatter};
 use std::mem::replace;
 use std::ops::Sub;
+use std::str::FromStr;
 use std::time::Duration;
 
-use chrono::{DateTime, FixedOffset, Local, Timelike};
+use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone};
 
 use crate::taskregistry::State::{DayTracking, Idle, TaskActive};
-use crate::timelog::{LogEvent, TimelogEntry};
+use crate::timelog::{parse_duration, Clock, LogEvent, TimelogEntry};
 
-const PAUSE_TASK_NAME: &str = "Pause";
-const UNDEFINED_TASK_NAME: &str = "n/n";
+/// Names of the pseudo-tasks collecting pauses and work time without an active task.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct PseudoNames {
+    pub pause: String,
+    pub undefined: String,
+}
+
+impl Default for PseudoNames {
+    fn default() -> Self {
+        PseudoNames {
+            pause: "Pause".to_owned(),
+            undefined: "n/n".to_owned(),
+        }
+    }
+}
 
 #[derive(Eq, PartialEq, Hash, Debug, Clone)]
 pub struct Task {
@@ -30,14 +42,38 @@ impl Task {
     }
 }
 
-impl fmt::Display for Task {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        let secs = self.duration.as_secs();
-        let mins = secs / 60;
-        let m = mins % 60;
-        let h = mins / 60;
-        let a = if self.active { "*" } else { "" };
-        write!(f, "{:02}:{:02}{}\t{}", h, m, a, self.name)
+/// How a day is handled that was not ended with `off`, when the next day starts or when the log
+/// ends on a past day.
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+pub enum OpenDayPolicy {
+    /// Refuse a new day; count a past last day up to now.
+    #[default]
+    Error,
+    /// End the day at its last entry.
+    LastEntry,
+    /// End the day at the given time of day, or at its last entry if that is later.
+    At(NaiveTime),
+    /// End the day once the given work time is reached, or at its last entry if that is later.
+    After(Duration),
+}
+
+impl FromStr for OpenDayPolicy {
+    type Err = String;
+
+    /// Parses `error`, `last-entry`, `at:HH:MM` or `after:H:MM`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            _ if s == "error" => Ok(OpenDayPolicy::Error),
+            _ if s == "last-entry" => Ok(OpenDayPolicy::LastEntry),
+            Some(("at", time)) => NaiveTime::parse_from_str(time, "%H:%M")
+                .map(OpenDayPolicy::At)
+                .map_err(|err| format!("invalid time {:?}: {}", time, err)),
+            Some(("after", duration)) => parse_duration(duration).map(OpenDayPolicy::After),
+            _ => Err(format!(
+                "unknown policy {:?}, expected error, last-entry, at:HH:MM or after:H:MM",
+                s
+            )),
+        }
     }
 }
 
@@ -53,43 +89,105 @@ pub struct TaskRegistryBuilder {
     state: State,
     current_task_name: Option<String>,
     work_start_time: Option<DateTime<FixedOffset>>,
+    last_time: Option<DateTime<FixedOffset>>,
     task_registry: TaskRegistry,
+    policy: OpenDayPolicy,
+    clock: Clock,
+    pseudo_names: PseudoNames,
+    break_reason: Option<String>,
+    pending_estimates: Vec<(String, Duration)>,
+    warnings: Vec<String>,
+}
+
+impl Default for TaskRegistryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TaskRegistryBuilder {
     pub fn new() -> TaskRegistryBuilder {
+        TaskRegistryBuilder::with_options(
+            OpenDayPolicy::Error,
+            Clock::System,
+            PseudoNames::default(),
+        )
+    }
+
+    pub fn with_options(
+        policy: OpenDayPolicy,
+        clock: Clock,
+        pseudo_names: PseudoNames,
+    ) -> TaskRegistryBuilder {
         TaskRegistryBuilder {
             start_time: None,
             state: Idle,
             current_task_name: None,
             work_start_time: None,
-            task_registry: TaskRegistry::new(),
+            last_time: None,
+            task_registry: TaskRegistry::new(pseudo_names.clone()),
+            policy,
+            clock,
+            pseudo_names,
+            break_reason: None,
+            pending_estimates: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
+    /// Adds the next entry of the log. Entries are expected in chronological order, which the
+    /// readers of the log check beforehand.
     pub fn add_entry(&mut self, entry: &TimelogEntry) -> Result<Option<TaskRegistry>, String> {
         let mut result = None;
+        if self.state != Idle && self.policy != OpenDayPolicy::Error {
+            match entry.event {
+                LogEvent::On => self.close_open_day(Some(&entry.time))?,
+                LogEvent::Start(_) | LogEvent::Resume if self.is_later_date(&entry.time) => {
+                    // The open day ends, and the entry starts the next one as if preceded by "on"
+                    self.close_open_day(Some(&entry.time))?;
+                    result = self.add_entry(&TimelogEntry::new(&entry.time, LogEvent::On))?;
+                    if entry.event == LogEvent::Resume {
+                        return Ok(result);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.last_time = Some(entry.time);
+
+        if let LogEvent::Estimate { name, duration } = &entry.event {
+            if self.state == Idle {
+                // Between days the estimate belongs to the day started next
+                self.pending_estimates.push((name.to_owned(), *duration));
+            } else {
+                self.task_registry.set_estimate(name, *duration);
+            }
+            return Ok(None);
+        }
+
         self.state = match self.state {
             Idle => match &entry.event {
                 LogEvent::On => {
                     if !self.task_registry.work_times.is_empty() {
-                        result = Some(replace(&mut self.task_registry, TaskRegistry::new()));
+                        let registry = TaskRegistry::new(self.pseudo_names.clone());
+                        result = Some(replace(&mut self.task_registry, registry));
                     }
+                    self.break_reason = None;
                     self.start_work_time(entry);
-                    self.start_task(&entry.time, PAUSE_TASK_NAME);
-                    self.stop_task(PAUSE_TASK_NAME, &entry.time)?;
-                    self.start_task(&entry.time, UNDEFINED_TASK_NAME);
+                    self.start_task(&entry.time, self.pseudo_names.pause.clone());
+                    self.stop_pause(&entry.time)?;
+                    self.start_task(&entry.time, self.pseudo_names.undefined.clone());
                     DayTracking
                 }
                 LogEvent::Resume => {
                     self.start_work_time(entry);
-                    self.stop_task(PAUSE_TASK_NAME, &entry.time)?;
-                    self.start_task(&entry.time, UNDEFINED_TASK_NAME);
+                    self.stop_pause(&entry.time)?;
+                    self.start_task(&entry.time, self.pseudo_names.undefined.clone());
                     DayTracking
                 }
                 LogEvent::Start(name) => {
                     self.start_work_time(entry);
-                    self.stop_task(PAUSE_TASK_NAME, &entry.time)?;
+                    self.stop_pause(&entry.time)?;
                     self.start_task(&entry.time, name);
                     TaskActive
                 }
@@ -101,19 +199,20 @@ impl TaskRegistryBuilder {
                 }
             },
             DayTracking => match &entry.event {
-                LogEvent::Off => {
-                    self.stop_task(UNDEFINED_TASK_NAME, &entry.time)?;
-                    self.start_task(&entry.time, PAUSE_TASK_NAME);
-                    self.stop_work_time(entry);
+                LogEvent::Off(reason) => {
+                    self.stop_task(&self.pseudo_names.undefined.clone(), &entry.time)?;
+                    self.start_pause(&entry.time, reason);
+                    self.stop_work_time(entry)?;
                     Idle
                 }
                 LogEvent::OffSnapshot => {
-                    self.record_task_time(UNDEFINED_TASK_NAME, &entry.time, true)?;
-                    self.stop_work_time(entry);
+                    let name = self.pseudo_names.undefined.clone();
+                    self.record_task_time(&name, &entry.time, true)?;
+                    self.stop_work_time(entry)?;
                     Idle
                 }
                 LogEvent::Start(name) => {
-                    self.stop_task(UNDEFINED_TASK_NAME, &entry.time)?;
+                    self.stop_task(&self.pseudo_names.undefined.clone(), &entry.time)?;
                     self.start_task(&entry.time, name);
                     TaskActive
                 }
@@ -127,19 +226,19 @@ impl TaskRegistryBuilder {
             TaskActive => match &entry.event {
                 LogEvent::Stop => {
                     self.stop_current_task(&entry.time)?;
-                    self.start_task(&entry.time, UNDEFINED_TASK_NAME);
+                    self.start_task(&entry.time, self.pseudo_names.undefined.clone());
                     DayTracking
                 }
-                LogEvent::Off => {
+                LogEvent::Off(reason) => {
                     self.stop_current_task(&entry.time)?;
-                    self.start_task(&entry.time, PAUSE_TASK_NAME);
-                    self.stop_work_time(entry);
+                    self.start_pause(&entry.time, reason);
+                    self.stop_work_time(entry)?;
                     Idle
                 }
                 LogEvent::OffSnapshot => {
                     let name = self.current_task_name.as_ref().unwrap().to_string();
                     self.record_task_time(&name, &entry.time, true)?;
-                    self.stop_work_time(entry);
+                    self.stop_work_time(entry)?;
                     Idle
                 }
                 LogEvent::Start(name) => {
@@ -151,9 +250,7 @@ impl TaskRegistryBuilder {
                     let name = from
                         .as_ref()
                         .or(self.current_task_name.as_ref())
-                        .ok_or(format!(
-                            "No task active while trying to rename current task"
-                        ))?;
+                        .ok_or("No task active while trying to rename current task")?;
                     self.task_registry.rename_task(to, name)?;
                     if from.is_none() {
                         self.current_task_name = Some(to.to_owned());
@@ -168,35 +265,90 @@ impl TaskRegistryBuilder {
                 }
             },
         };
+        if self.state != Idle {
+            for (name, duration) in self.pending_estimates.drain(..) {
+                self.task_registry.set_estimate(&name, duration);
+            }
+        }
 
         Ok(result)
     }
 
+    /// Returns the registry of the current day. A day still in progress is accounted up to the
+    /// current time, or up to its last entry if that lies in the future.
     pub fn finish(&mut self) -> TaskRegistry {
+        let now = self.clock.now();
+        let past_day = self
+            .last_time
+            .is_some_and(|last_time| last_time.date().naive_local() < now.date().naive_local());
+        if self.state != Idle && past_day && self.policy != OpenDayPolicy::Error {
+            self.close_open_day(None)
+                .expect("closing at or after the last entry is always valid");
+        }
         if self.state != Idle {
-            let now = Local::now()
-                .with_second(0)
-                .unwrap()
-                .with_nanosecond(0)
-                .unwrap();
-            let time: DateTime<FixedOffset> = now.into();
-            let time = time.with_timezone(now.offset());
+            let time = self.last_time.map_or(now, |last_time| last_time.max(now));
             self.add_entry(&TimelogEntry::new(&time, LogEvent::OffSnapshot))
-                .unwrap();
+                .expect("snapshot at or after the last entry is always valid");
         }
 
         self.start_time = None;
-        replace(&mut self.task_registry, TaskRegistry::new())
+        replace(
+            &mut self.task_registry,
+            TaskRegistry::new(self.pseudo_names.clone()),
+        )
+    }
+
+    /// Returns the warnings about automatically closed days since the last call.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Ends the current day according to the policy, no later than the given time if possible.
+    fn close_open_day(&mut self, before: Option<&DateTime<FixedOffset>>) -> Result<(), String> {
+        let last_time = self.last_time.ok_or("No day to close")?;
+        let work_start_time = self.work_start_time.ok_or("No work time to close")?;
+        let close_time = match self.policy {
+            OpenDayPolicy::Error | OpenDayPolicy::LastEntry => None,
+            OpenDayPolicy::At(time) => work_start_time
+                .offset()
+                .from_local_datetime(&work_start_time.date().naive_local().and_time(time))
+                .single(),
+            OpenDayPolicy::After(target) => {
+                let remaining = target.saturating_sub(self.task_registry.work_duration);
+                chrono::Duration::from_std(remaining)
+                    .ok()
+                    .and_then(|remaining| work_start_time.checked_add_signed(remaining))
+            }
+        };
+        let close_time = close_time
+            .filter(|time| *time >= last_time && before.is_none_or(|before| time <= before))
+            .unwrap_or(last_time);
+
+        self.add_entry(&TimelogEntry::new(&close_time, LogEvent::Off(None)))?;
+        self.warnings.push(format!(
+            "Day {} was not ended with \"off\", closed automatically at {}",
+            close_time.date().naive_local(),
+            close_time.format("%R")
+        ));
+        Ok(())
+    }
+
+    /// Tells whether the given time is on a later date than the last entry.
+    fn is_later_date(&self, time: &DateTime<FixedOffset>) -> bool {
+        self.last_time
+            .is_some_and(|last_time| last_time.date().naive_local() < time.date().naive_local())
     }
 
     fn start_work_time(&mut self, entry: &TimelogEntry) {
         self.work_start_time = Some(entry.time);
     }
 
-    fn stop_work_time(&mut self, entry: &TimelogEntry) {
-        self.task_registry
-            .add_work_time(self.work_start_time.unwrap(), entry.time);
-        self.work_start_time = None;
+    fn stop_work_time(&mut self, entry: &TimelogEntry) -> Result<(), String> {
+        let start_time = self
+            .work_start_time
+            .take()
+            .ok_or("Work time stopped without being started")?;
+        self.task_registry.add_work_time(start_time, entry.time)
     }
 
     fn stop_current_task(&mut self, time: &DateTime<FixedOffset>) -> Result<(), String> {
@@ -223,16 +375,29 @@ impl TaskRegistryBuilder {
             .record_task_time(name, duration, keep_active)
     }
 
-    fn start_task<T: ToString + AsRef<str>>(
-        &mut self,
-        time: &DateTime<FixedOffset>,
-        name: T,
-    ) -> () {
+    fn start_task<T: ToString + AsRef<str>>(&mut self, time: &DateTime<FixedOffset>, name: T) {
         self.start_time = Some(*time);
         self.current_task_name = Some(name.to_string());
-        let active = PAUSE_TASK_NAME != name.as_ref();
+        let active = self.pseudo_names.pause != name.as_ref();
         self.task_registry.add_task(name, active);
     }
+
+    fn start_pause(&mut self, time: &DateTime<FixedOffset>, reason: &Option<String>) {
+        self.start_task(time, self.pseudo_names.pause.clone());
+        self.break_reason = reason.clone();
+    }
+
+    /// Ends the pause, accounting its time to the pause task and to the break's reason.
+    fn stop_pause(&mut self, time: &DateTime<FixedOffset>) -> Result<(), String> {
+        let start_time = self
+            .start_time
+            .ok_or("Pause stopped without being started")?;
+        self.stop_task(&self.pseudo_names.pause.clone(), time)?;
+        let duration = (*time - start_time).to_std().unwrap_or_default();
+        self.task_registry
+            .add_break(self.break_reason.take(), duration);
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -242,16 +407,22 @@ pub struct TaskRegistry {
     work_times: Vec<(DateTime<FixedOffset>, DateTime<FixedOffset>)>,
     work_duration: Duration,
     last_active: Option<usize>,
+    estimates: Vec<(String, Duration)>,
+    breaks: Vec<(Option<String>, Duration)>,
+    pseudo_names: PseudoNames,
 }
 
 impl TaskRegistry {
-    fn new() -> TaskRegistry {
+    fn new(pseudo_names: PseudoNames) -> TaskRegistry {
         TaskRegistry {
             tasks: Vec::new(),
             names: HashMap::new(),
             work_times: Vec::new(),
             work_duration: Duration::from_secs(0),
             last_active: None,
+            estimates: Vec::new(),
+            breaks: Vec::new(),
+            pseudo_names,
         }
     }
 
@@ -260,10 +431,7 @@ impl TaskRegistry {
     }
 
     pub fn get_start_time(&self) -> Result<DateTime<FixedOffset>, String> {
-        let times = self
-            .work_times
-            .first()
-            .ok_or_else(|| format!("No work times recorded"))?;
+        let times = self.work_times.first().ok_or("No work times recorded")?;
         Ok(times.0)
     }
 
@@ -279,6 +447,85 @@ impl TaskRegistry {
         self.last_active.map(|i| self.tasks.get(i).unwrap().clone())
     }
 
+    pub fn get_active(&self) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.active)
+    }
+
+    /// Tells whether the given task is the pseudo-task collecting the time between work periods.
+    pub fn is_pause_task(&self, task: &Task) -> bool {
+        task.name == self.pseudo_names.pause
+    }
+
+    /// Tells whether the given task is one of the pseudo-tasks for pauses and unassigned work time.
+    pub fn is_pseudo_task(&self, task: &Task) -> bool {
+        self.is_pause_task(task) || self.is_undefined_task(task)
+    }
+
+    /// Tells whether the given task is the pseudo-task collecting work time without active task.
+    pub fn is_undefined_task(&self, task: &Task) -> bool {
+        task.name == self.pseudo_names.undefined
+    }
+
+    /// Returns the time of the breaks per reason given with `off`, `None` for breaks without
+    /// reason, in order of first occurrence.
+    pub fn get_breaks(&self) -> &[(Option<String>, Duration)] {
+        self.breaks.as_slice()
+    }
+
+    fn add_break(&mut self, reason: Option<String>, duration: Duration) {
+        if duration.is_zero() {
+            return;
+        }
+        match self.breaks.iter_mut().find(|(r, _)| *r == reason) {
+            Some(category) => category.1 += duration,
+            None => self.breaks.push((reason, duration)),
+        }
+    }
+
+    /// Adds the tasks, work times, estimates and breaks of a later part of the same day, e.g.
+    /// after an `off` followed by `on`.
+    pub fn merge(&mut self, other: &TaskRegistry) {
+        for task in &other.tasks {
+            let i = match self.names.get(&task.name) {
+                Some(&i) => i,
+                None => {
+                    self.names.insert(task.name.clone(), self.tasks.len());
+                    self.tasks.push(Task {
+                        duration: Duration::from_secs(0),
+                        ..task.clone()
+                    });
+                    self.tasks.len() - 1
+                }
+            };
+            let merged = &mut self.tasks[i];
+            merged.duration += task.duration;
+            merged.active = task.active;
+        }
+        if let Some(i) = other.last_active {
+            self.last_active = self.names.get(&other.tasks[i].name).copied();
+        }
+        self.work_times.extend_from_slice(&other.work_times);
+        self.work_duration += other.work_duration;
+        for (name, duration) in &other.estimates {
+            self.set_estimate(name, *duration);
+        }
+        for (reason, duration) in &other.breaks {
+            self.add_break(reason.clone(), *duration);
+        }
+    }
+
+    /// Returns the task estimates declared on this day, in declaration order.
+    pub fn get_estimates(&self) -> &[(String, Duration)] {
+        self.estimates.as_slice()
+    }
+
+    fn set_estimate(&mut self, name: &str, duration: Duration) {
+        match self.estimates.iter_mut().find(|(n, _)| n == name) {
+            Some(estimate) => estimate.1 = duration,
+            None => self.estimates.push((name.to_owned(), duration)),
+        }
+    }
+
     fn record_task_time(
         &mut self,
         name: &str,
@@ -313,7 +560,7 @@ impl TaskRegistry {
                 i
             }
         };
-        if UNDEFINED_TASK_NAME == name.as_ref() {
+        if self.pseudo_names.undefined == name.as_ref() {
             self.last_active = None;
         } else if active {
             self.last_active = Some(i);
@@ -327,17 +574,102 @@ impl TaskRegistry {
             .ok_or(format!("Couldn't find task name '{}' while renaming", from))?;
         self.names.insert(to.to_owned(), i);
         self.tasks.get_mut(i).unwrap().name = to.to_owned();
+        if let Some(estimate) = self.estimates.iter_mut().find(|(n, _)| n == from) {
+            estimate.0 = to.to_owned();
+        }
         Ok(())
     }
 
-    fn add_work_time(&mut self, from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) {
-        self.work_duration += to.sub(from).to_std().unwrap();
+    fn add_work_time(
+        &mut self,
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+    ) -> Result<(), String> {
+        self.work_duration += to.sub(from).to_std().map_err(|_| {
+            format!(
+                "Work time ends at {} before it starts at {}",
+                to.format("%FT%R%z"),
+                from.format("%FT%R%z")
+            )
+        })?;
         if let Some((_, last_to)) = self.work_times.last_mut() {
             if from == *last_to {
                 *last_to = to;
-                return;
+                return Ok(());
             }
         }
         self.work_times.push((from, to));
+        Ok(())
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct TaskTotal {
+    pub name: String,
+    pub duration: Duration,
+    pub estimate: Option<Duration>,
+    pub pseudo: bool,
+}
+
+/// Task durations and estimates accumulated over several days.
+#[derive(Debug, Clone, Default)]
+pub struct TaskTotals {
+    tasks: Vec<TaskTotal>,
+    names: HashMap<String, usize>,
+}
+
+impl TaskTotals {
+    pub fn new() -> TaskTotals {
+        TaskTotals::default()
+    }
+
+    /// Adds the work time of all tasks of the given day, leaving out pauses.
+    pub fn add(&mut self, registry: &TaskRegistry) {
+        for task in registry.get_tasks() {
+            if !registry.is_pause_task(task) {
+                let total = self.get_or_insert(&task.name);
+                total.duration += task.duration;
+                total.pseudo = registry.is_pseudo_task(task);
+            }
+        }
+        for (name, duration) in registry.get_estimates() {
+            self.get_or_insert(name).estimate = Some(*duration);
+        }
+    }
+
+    /// Adds totals collected separately, e.g. from another person's log.
+    pub fn add_totals(&mut self, other: &TaskTotals) {
+        for task in other.get_tasks() {
+            let total = self.get_or_insert(&task.name);
+            total.duration += task.duration;
+            total.estimate = total.estimate.or(task.estimate);
+            total.pseudo = task.pseudo;
+        }
+    }
+
+    pub fn get_tasks(&self) -> &[TaskTotal] {
+        self.tasks.as_slice()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TaskTotal> {
+        self.names.get(name).map(|&i| &self.tasks[i])
+    }
+
+    fn get_or_insert(&mut self, name: &str) -> &mut TaskTotal {
+        let i = match self.names.get(name) {
+            Some(&i) => i,
+            None => {
+                let i = self.tasks.len();
+                self.names.insert(name.to_owned(), i);
+                self.tasks.push(TaskTotal {
+                    name: name.to_owned(),
+                    duration: Duration::from_secs(0),
+                    estimate: None,
+                    pseudo: false,
+                });
+                i
+            }
+        };
+        &mut self.tasks[i]
     }
 }
@@ -0,0 +1,248 @@
+use std::io;
+use std::str::FromStr;
+
+use crate::print::PrintOptions;
+use crate::taskregistry::{Task, TaskRegistry};
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+enum DayField {
+    Date,
+    WorkTime,
+    Intervals,
+    Start,
+    End,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+enum TaskField {
+    Number,
+    Name,
+    Time,
+    Active,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+enum Part<F> {
+    Text(String),
+    Field(F),
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+enum Section {
+    Day(Part<DayField>),
+    Tasks(Vec<Part<TaskField>>),
+}
+
+/// A user-defined output layout for a day.
+///
+/// Placeholders in braces are replaced by day fields: `{date}`, `{work_time}`, `{start}`, `{end}`
+/// and `{intervals}`. The text between `{tasks}` and `{end_tasks}` is repeated for every listed
+/// task, with the task fields `{n}`, `{name}`, `{time}` and `{active}`. Literal braces are
+/// written as `{{` and `}}`.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Template {
+    sections: Vec<Section>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sections = Vec::new();
+        let mut loop_parts: Option<Vec<Part<TaskField>>> = None;
+        let mut text = String::new();
+
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err("unmatched \"}\", write \"}}\" for a literal brace".to_owned()),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed placeholder \"{{{}\"", name)),
+                        }
+                    }
+
+                    let text = std::mem::take(&mut text);
+                    match (&mut loop_parts, name.as_str()) {
+                        (None, "tasks") => {
+                            sections.push(Section::Day(Part::Text(text)));
+                            loop_parts = Some(Vec::new());
+                        }
+                        (None, _) => {
+                            sections.push(Section::Day(Part::Text(text)));
+                            sections.push(Section::Day(Part::Field(parse_day_field(&name)?)));
+                        }
+                        (Some(_), "tasks") => return Err("nested \"{tasks}\"".to_owned()),
+                        (Some(parts), "end_tasks") => {
+                            parts.push(Part::Text(text));
+                            sections.push(Section::Tasks(loop_parts.take().unwrap()));
+                        }
+                        (Some(parts), _) => {
+                            parts.push(Part::Text(text));
+                            parts.push(Part::Field(parse_task_field(&name)?));
+                        }
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+
+        if loop_parts.is_some() {
+            return Err("\"{tasks}\" without \"{end_tasks}\"".to_owned());
+        }
+        sections.push(Section::Day(Part::Text(text)));
+
+        Ok(Template { sections })
+    }
+}
+
+fn parse_day_field(name: &str) -> Result<DayField, String> {
+    match name {
+        "date" => Ok(DayField::Date),
+        "work_time" => Ok(DayField::WorkTime),
+        "intervals" => Ok(DayField::Intervals),
+        "start" => Ok(DayField::Start),
+        "end" => Ok(DayField::End),
+        "end_tasks" => Err("\"{end_tasks}\" without \"{tasks}\"".to_owned()),
+        "n" | "name" | "time" | "active" => Err(format!(
+            "task field \"{{{}}}\" used outside of \"{{tasks}}\"",
+            name
+        )),
+        _ => Err(format!("unknown placeholder \"{{{}}}\"", name)),
+    }
+}
+
+fn parse_task_field(name: &str) -> Result<TaskField, String> {
+    match name {
+        "n" => Ok(TaskField::Number),
+        "name" => Ok(TaskField::Name),
+        "time" => Ok(TaskField::Time),
+        "active" => Ok(TaskField::Active),
+        _ => Err(format!("unknown task placeholder \"{{{}}}\"", name)),
+    }
+}
+
+impl Template {
+    /// Writes the day using the template, listing the given tasks the way the table output would.
+    /// A pause task at index 0 is left unnumbered.
+    pub fn render(
+        &self,
+        mut w: impl io::Write,
+        registry: &TaskRegistry,
+        tasks: &[(usize, &Task)],
+        options: &PrintOptions,
+    ) -> io::Result<()> {
+        let work_times = registry.get_work_times();
+
+        for section in &self.sections {
+            match section {
+                Section::Day(Part::Text(text)) => write!(&mut w, "{}", text)?,
+                Section::Day(Part::Field(field)) => match field {
+                    DayField::Date => {
+                        if let Ok(start) = registry.get_start_time() {
+                            write!(&mut w, "{}", start.format("%F"))?;
+                        }
+                    }
+                    DayField::WorkTime => write!(
+                        &mut w,
+                        "{}",
+                        options
+                            .duration_format
+                            .format(&registry.get_work_duration())
+                    )?,
+                    DayField::Intervals => {
+                        let intervals: Vec<String> = work_times
+                            .iter()
+                            .map(|(on, off)| format!("{}-{}", on.format("%R"), off.format("%R")))
+                            .collect();
+                        write!(&mut w, "{}", intervals.join(", "))?;
+                    }
+                    DayField::Start => {
+                        if let Some((on, _)) = work_times.first() {
+                            write!(&mut w, "{}", on.format("%R"))?;
+                        }
+                    }
+                    DayField::End => {
+                        if let Some((_, off)) = work_times.last() {
+                            write!(&mut w, "{}", off.format("%R"))?;
+                        }
+                    }
+                },
+                Section::Tasks(parts) => {
+                    for (n, task) in tasks {
+                        for part in parts {
+                            match part {
+                                Part::Text(text) => write!(&mut w, "{}", text)?,
+                                Part::Field(TaskField::Number) if *n == 0 => {}
+                                Part::Field(TaskField::Number) => write!(&mut w, "{}", n)?,
+                                Part::Field(TaskField::Name) => write!(&mut w, "{}", task.name)?,
+                                Part::Field(TaskField::Time) => write!(
+                                    &mut w,
+                                    "{}",
+                                    options.duration_format.format(&task.duration)
+                                )?,
+                                Part::Field(TaskField::Active) if task.active => {
+                                    write!(&mut w, "*")?
+                                }
+                                Part::Field(TaskField::Active) => {}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |s: &str| s.parse::<Template>().unwrap_err();
+        assert_eq!(parse("{dat}"), "unknown placeholder \"{dat}\"");
+        assert_eq!(parse("{date"), "unclosed placeholder \"{date\"");
+        assert_eq!(
+            parse("{name}"),
+            "task field \"{name}\" used outside of \"{tasks}\""
+        );
+        assert_eq!(
+            parse("{tasks}{name}"),
+            "\"{tasks}\" without \"{end_tasks}\""
+        );
+        assert_eq!(parse("{end_tasks}"), "\"{end_tasks}\" without \"{tasks}\"");
+        assert_eq!(parse("{tasks}{tasks}"), "nested \"{tasks}\"");
+        assert_eq!(
+            parse("{tasks}{date}{end_tasks}"),
+            "unknown task placeholder \"{date}\""
+        );
+        assert_eq!(
+            parse("a}b"),
+            "unmatched \"}\", write \"}}\" for a literal brace"
+        );
+    }
+
+    #[test]
+    fn test_literal_braces() {
+        let template: Template = "{{date}} }}".parse().unwrap();
+        assert_eq!(
+            template.sections,
+            vec![Section::Day(Part::Text("{date} }".to_owned()))]
+        );
+    }
+}
@@ -1,18 +1,47 @@
-use crate::timelog::LogEvent::{Cancel, Resume, Off, On, Rename, Start, Stop};
-use chrono::{DateTime, FixedOffset};
+use std::fmt;
+use std::time::Duration;
+
+use crate::timelog::LogEvent::{
+    Cancel, Estimate, Off, OffSnapshot, On, Rename, Resume, Start, Stop,
+};
+use chrono::{DateTime, FixedOffset, Local, Timelike};
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub enum LogEvent {
     On,
-    Off,
+    Off(Option<String>),
     Resume,
     Cancel,
     Start(String),
     Stop,
     Rename { to: String, from: Option<String> },
+    Estimate { name: String, duration: Duration },
     OffSnapshot,
 }
 
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            On => write!(f, "on"),
+            Off(Some(reason)) => write!(f, "off\t{}", reason),
+            Off(None) | OffSnapshot => write!(f, "off"),
+            Resume => write!(f, "resume"),
+            Cancel => write!(f, "cancel"),
+            Start(name) => write!(f, "start\t{}", name),
+            Stop => write!(f, "stop"),
+            Rename { to, from: None } => write!(f, "rename\t{}", to),
+            Rename {
+                to,
+                from: Some(from),
+            } => write!(f, "rename\t{}\t{}", to, from),
+            Estimate { name, duration } => {
+                let mins = duration.as_secs() / 60;
+                write!(f, "estimate\t{}\t{}:{:02}", name, mins / 60, mins % 60)
+            }
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct TimelogEntry {
     pub time: DateTime<FixedOffset>,
@@ -47,7 +76,10 @@ impl TimelogEntry {
         let event_part = part_it.next().ok_or("expected event part")?;
         let event: LogEvent = match event_part {
             "on" => On,
-            "off" => Off,
+            "off" => Off(part_it
+                .next()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned())),
             "resume" => Resume,
             "cancel" => Cancel,
             "start" => {
@@ -63,6 +95,12 @@ impl TimelogEntry {
                 let from = part_it.next().map(|s| s.to_owned());
                 Rename { to, from }
             }
+            "estimate" => {
+                let name = part_it.next().ok_or("expected task name")?.to_owned();
+                let duration = part_it.next().ok_or("expected estimated duration")?;
+                let duration = parse_duration(duration)?;
+                Estimate { name, duration }
+            }
             &_ => return Err("unexpected event: ".to_owned() + event_part),
         };
 
@@ -79,6 +117,63 @@ impl TimelogEntry {
     }
 }
 
+impl fmt::Display for TimelogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}",
+            self.time.format("%Y-%m-%dT%H:%M%z"),
+            self.event
+        )
+    }
+}
+
+/// Returns the current local time, truncated to full minutes.
+pub fn current_minute() -> DateTime<FixedOffset> {
+    let now = Local::now()
+        .with_second(0)
+        .unwrap()
+        .with_nanosecond(0)
+        .unwrap();
+    let time: DateTime<FixedOffset> = now.into();
+    time.with_timezone(now.offset())
+}
+
+/// Source of the current time. A fixed clock makes reports reproducible.
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+pub enum Clock {
+    /// The local system time.
+    #[default]
+    System,
+    /// The given time, as if the program ran at that moment.
+    Fixed(DateTime<FixedOffset>),
+}
+
+impl Clock {
+    /// Returns the current time, truncated to full minutes for the system clock.
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        match self {
+            Clock::System => current_minute(),
+            Clock::Fixed(time) => *time,
+        }
+    }
+}
+
+/// Parses a duration given as hours and minutes, e.g. `7:45` or `07:45`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("could not parse duration: {}", s);
+    let (h, m) = s.split_once(':').ok_or_else(invalid)?;
+    if m.len() != 2 {
+        return Err(invalid());
+    }
+    let h = h.parse::<u64>().map_err(|_| invalid())?;
+    let m = m.parse::<u64>().map_err(|_| invalid())?;
+    if m >= 60 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs((h * 60 + m) * 60))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,4 +217,41 @@ mod tests {
         let expected = Err("unexpected trailing content: this is trailing".to_owned());
         assert_eq!(entry, expected);
     }
+
+    #[test]
+    fn test_parse_line_estimate() {
+        let entry =
+            TimelogEntry::parse_from_str("2019-11-10T16:04+0100\testimate\tRefactor code\t12:30");
+        let expected = TimelogEntry::of_str(
+            "2019-11-10T16:04:00+01:00",
+            Estimate {
+                name: "Refactor code".to_owned(),
+                duration: Duration::from_secs((12 * 60 + 30) * 60),
+            },
+        );
+        assert_eq!(entry, Ok(expected));
+    }
+
+    #[test]
+    fn test_format_line() {
+        let lines = [
+            "2019-11-10T16:04+0100\ton",
+            "2019-11-10T16:04+0100\toff",
+            "2019-11-10T16:04+0100\toff\tlunch break",
+            "2019-11-10T16:04+0100\tstart\tRefactor code",
+            "2019-11-10T16:04+0100\trename\tRefactoring\tRefactor code",
+            "2019-11-10T16:04+0100\testimate\tRefactoring\t2:05",
+        ];
+        for line in lines.iter() {
+            let entry = TimelogEntry::parse_from_str(line).unwrap();
+            assert_eq!(entry.to_string(), *line);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("1:5").is_err());
+        assert!(parse_duration("1:75").is_err());
+        assert!(parse_duration("90").is_err());
+    }
 }
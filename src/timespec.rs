@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveTime, TimeZone, Weekday};
+
+use crate::timelog::parse_duration;
+
+/// Parses a time given on the command line, relative to the given current time:
+///
+/// * `now`
+/// * a full timestamp as in the log file, as `2019-11-28T09:30+0100`
+/// * a time of day, as `09:30`
+/// * an offset from now, as `-15m`, `-1h30m`, `+5m` or `-1:30`
+/// * a day followed by a time of day, as `yesterday 17:45`, `today 08:00`, `monday 09:00` or
+///   `2019-11-28 09:30`; weekdays refer to the most recent such day, including today
+///
+/// Except for full timestamps, the result has the UTC offset of the current time.
+pub fn parse_time_spec(
+    spec: &str,
+    now: &DateTime<FixedOffset>,
+) -> Result<DateTime<FixedOffset>, String> {
+    let spec = spec.trim();
+    let time = if spec == "now" {
+        Some(*now)
+    } else if let Ok(time) = DateTime::parse_from_str(spec, "%Y-%m-%dT%H:%M%z") {
+        Some(time)
+    } else if let Some(offset) = spec.strip_prefix('-') {
+        parse_offset(offset).and_then(|offset| now.checked_sub_signed(offset))
+    } else if let Some(offset) = spec.strip_prefix('+') {
+        parse_offset(offset).and_then(|offset| now.checked_add_signed(offset))
+    } else {
+        parse_day_and_time(spec, now)
+    };
+
+    time.ok_or_else(|| {
+        format!(
+            "Invalid time given: {:?}, expected e.g. \"09:30\", \"-15m\" or \"yesterday 17:45\"",
+            spec
+        )
+    })
+}
+
+/// Parses durations like `15m`, `1h`, `1h30m` or `1:30`.
+fn parse_offset(s: &str) -> Option<chrono::Duration> {
+    if s.contains(':') {
+        let duration = parse_duration(s).ok()?;
+        return chrono::Duration::from_std(duration).ok();
+    }
+
+    let mut minutes = 0;
+    let mut number = String::new();
+    let mut last_unit = None;
+    for c in s.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm'
+                if !number.is_empty() && last_unit.is_none_or(|unit| unit == 'h' && c == 'm') =>
+            {
+                let value: i64 = number.parse().ok()?;
+                minutes += if c == 'h' { value * 60 } else { value };
+                number.clear();
+                last_unit = Some(c);
+            }
+            _ => return None,
+        }
+    }
+    if !number.is_empty() || last_unit.is_none() {
+        return None;
+    }
+
+    chrono::Duration::from_std(Duration::from_secs(minutes as u64 * 60)).ok()
+}
+
+fn parse_day_and_time(s: &str, now: &DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    let today = now.naive_local().date();
+    // Only a full date may be joined to the time with a "T", as day names contain one
+    let split = s.split_once(' ').or_else(|| {
+        s.split_once('T')
+            .filter(|(day, _)| NaiveDate::parse_from_str(day, "%Y-%m-%d").is_ok())
+    });
+    let (date, time) = match split {
+        Some((day, time)) => (parse_day(day.trim(), today)?, time.trim()),
+        None => (today, s),
+    };
+    let time = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+    now.offset()
+        .from_local_datetime(&date.and_time(time))
+        .single()
+}
+
+fn parse_day(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match s.to_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        day => match day.parse::<Weekday>() {
+            Ok(weekday) => {
+                let days_back = (7 + today.weekday().num_days_from_monday()
+                    - weekday.num_days_from_monday())
+                    % 7;
+                today.checked_sub_signed(chrono::Duration::days(days_back as i64))
+            }
+            Err(_) => NaiveDate::parse_from_str(day, "%Y-%m-%d").ok(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(spec: &str) -> Result<String, String> {
+        // a Thursday
+        let now = DateTime::parse_from_rfc3339("2019-11-28T12:10:00+01:00").unwrap();
+        parse_time_spec(spec, &now).map(|time| time.format("%FT%R%z").to_string())
+    }
+
+    #[test]
+    fn test_time_of_day() {
+        assert_eq!(parse("now").unwrap(), "2019-11-28T12:10+0100");
+        assert_eq!(parse("09:30").unwrap(), "2019-11-28T09:30+0100");
+        assert_eq!(parse("today 23:59").unwrap(), "2019-11-28T23:59+0100");
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(parse("-15m").unwrap(), "2019-11-28T11:55+0100");
+        assert_eq!(parse("-1h").unwrap(), "2019-11-28T11:10+0100");
+        assert_eq!(parse("-1h30m").unwrap(), "2019-11-28T10:40+0100");
+        assert_eq!(parse("-1:30").unwrap(), "2019-11-28T10:40+0100");
+        assert_eq!(parse("+5m").unwrap(), "2019-11-28T12:15+0100");
+        assert_eq!(parse("-13h").unwrap(), "2019-11-27T23:10+0100");
+    }
+
+    #[test]
+    fn test_days() {
+        assert_eq!(parse("yesterday 17:45").unwrap(), "2019-11-27T17:45+0100");
+        assert_eq!(parse("Monday 09:00").unwrap(), "2019-11-25T09:00+0100");
+        assert_eq!(parse("Today 09:00").unwrap(), "2019-11-28T09:00+0100");
+        assert_eq!(parse("Tuesday 09:00").unwrap(), "2019-11-26T09:00+0100");
+        assert_eq!(parse("Thursday 09:00").unwrap(), "2019-11-28T09:00+0100");
+        assert_eq!(parse("thu 09:00").unwrap(), "2019-11-28T09:00+0100");
+        assert_eq!(parse("fri 09:00").unwrap(), "2019-11-22T09:00+0100");
+        assert_eq!(parse("2019-11-01 08:15").unwrap(), "2019-11-01T08:15+0100");
+        assert_eq!(parse("2019-11-01T08:15").unwrap(), "2019-11-01T08:15+0100");
+        assert_eq!(
+            parse("2019-07-01T08:15+0200").unwrap(),
+            "2019-07-01T08:15+0200"
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for spec in &[
+            "",
+            "9",
+            "25:00",
+            "-15",
+            "-m",
+            "-15x",
+            "-1m1h",
+            "-1h1h",
+            "tomorrow 09:00",
+            "yesterday",
+        ] {
+            assert!(parse(spec).is_err(), "{:?} should not parse", spec);
+        }
+    }
+}
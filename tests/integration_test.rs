@@ -1,10 +1,27 @@
+extern crate chrono;
+extern crate regex;
 extern crate timetrack;
 
+use chrono::{DateTime, NaiveDate};
+
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use regex::Regex;
+use timetrack::audit;
+use timetrack::audit::AuditOptions;
 use timetrack::cmd;
-use timetrack::cmd::SummaryScope;
+use timetrack::cmd::{DateRange, SummaryScope};
+use timetrack::fileread::ReadOptions;
+use timetrack::filewrite;
+use timetrack::merge;
+use timetrack::print::{DurationFormat, PrintOptions, TaskSort};
+use timetrack::serve;
+use timetrack::table::TableFormat;
+use timetrack::taskregistry::OpenDayPolicy;
+use timetrack::timelog::{Clock, LogEvent, TimelogEntry};
 
 #[test]
 fn test_summaries() {
@@ -30,7 +47,14 @@ fn test_summaries() {
     let expected = fs::read_to_string(expected_path).unwrap();
 
     let mut w: Vec<u8> = Vec::new();
-    cmd::summaries(&mut w, file, SummaryScope::Last(1)).unwrap();
+    cmd::summaries(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        SummaryScope::Last(1),
+        &PrintOptions::default(),
+    )
+    .unwrap();
 
     let result = String::from_utf8(w).unwrap();
 
@@ -61,9 +85,1114 @@ fn test_worklog() {
     let expected = fs::read_to_string(expected_path).unwrap();
 
     let mut w: Vec<u8> = Vec::new();
-    cmd::worklog(&mut w, file, SummaryScope::Last(2)).unwrap();
+    cmd::worklog(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        SummaryScope::Last(2),
+        &PrintOptions::default(),
+    )
+    .unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_serve_days() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let response = serve::handle_request("/days", file, &ReadOptions::default());
+    assert_eq!(response.status, 200);
+    assert_eq!(
+        response.body,
+        r#"[{"date":"2019-11-21","work_minutes":455},{"date":"2019-11-22","work_minutes":389},{"date":"2019-11-26","work_minutes":534},{"date":"2019-11-28","work_minutes":504}]"#
+    );
+
+    let response = serve::handle_request("/days/2019-11-23", file, &ReadOptions::default());
+    assert_eq!(response.status, 404);
+}
+
+#[test]
+fn test_complete_tasks() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    cmd::complete_tasks(&mut w, file).unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+    let names: Vec<&str> = result.lines().collect();
+
+    assert_eq!(
+        names[..4],
+        [
+            "BACKEND - logging framework",
+            "FRONTEND - release notes",
+            "FRONTEND - translations",
+            "Sprint Retro",
+        ]
+    );
+    assert_eq!(names.last(), Some(&"BACKEND-input-parsing"));
+    assert!(!names.contains(&"BACKEND-errors"));
+}
+
+#[test]
+fn test_continue_work() {
+    let path = env::temp_dir().join("timetrack_test_continue.csv");
+    let file = path.to_str().unwrap();
+    fs::write(
+        &path,
+        "2019-11-26T07:00+0100\ton\n\
+         2019-11-26T07:10+0100\tstart\tFRONTEND - error handling\n\
+         2019-11-26T07:48+0100\toff\n",
+    )
+    .unwrap();
+
+    // Nothing is reported when the entry cannot be added
+    let time = DateTime::parse_from_rfc3339("2019-11-26T07:30:00+01:00").unwrap();
+    let mut w: Vec<u8> = Vec::new();
+    let result = cmd::continue_work(&mut w, file, &ReadOptions::default(), &time);
+    assert!(result.unwrap_err().contains("earlier than the last entry"));
+    assert!(w.is_empty());
+
+    let time = DateTime::parse_from_rfc3339("2019-11-27T08:00:00+01:00").unwrap();
+    let mut w: Vec<u8> = Vec::new();
+    cmd::continue_work(&mut w, file, &ReadOptions::default(), &time).unwrap();
+    assert_eq!(
+        String::from_utf8(w).unwrap(),
+        "Continuing task \"FRONTEND - error handling\"\n"
+    );
+
+    let mut w: Vec<u8> = Vec::new();
+    let result = cmd::continue_work(&mut w, file, &ReadOptions::default(), &time);
+    assert_eq!(
+        result,
+        Err("Task \"FRONTEND - error handling\" is already active".to_owned())
+    );
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert!(content.ends_with(
+        "2019-11-26T07:48+0100\toff\n\n\
+         # Wednesday\n\
+         2019-11-27T08:00+0100\ton\n\
+         2019-11-27T08:00+0100\tstart\tFRONTEND - error handling\n"
+    ));
+}
+
+#[test]
+fn test_summary_markdown() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let expected_path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "expected",
+        "day_4_summary.md",
+    ]
+    .iter()
+    .collect();
+    let expected = fs::read_to_string(expected_path).unwrap();
+
+    let options = PrintOptions {
+        format: TableFormat::Markdown,
+        ..PrintOptions::default()
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::summaries(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        SummaryScope::Last(1),
+        &options,
+    )
+    .unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_team() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let expected_path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "expected",
+        "day_4_team.txt",
+    ]
+    .iter()
+    .collect();
+    let expected = fs::read_to_string(expected_path).unwrap();
+
+    let members = [
+        ("alice".to_owned(), file.to_owned()),
+        ("bob".to_owned(), file.to_owned()),
+    ];
+    let day_4 = NaiveDate::from_ymd(2019, 11, 28);
+    let range = DateRange {
+        from: Some(day_4),
+        to: Some(day_4),
+    };
+
+    let mut w: Vec<u8> = Vec::new();
+    cmd::team(
+        &mut w,
+        &members,
+        &ReadOptions::default(),
+        &range,
+        &PrintOptions::default(),
+    )
+    .unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_merge() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let expected = fs::read_to_string(d).unwrap();
+    let lines: Vec<&str> = expected.lines().collect();
+
+    // first two days, and the second day again with the rest of the file
+    let path_a = env::temp_dir().join("timetrack_test_merge_a.csv");
+    let path_b = env::temp_dir().join("timetrack_test_merge_b.csv");
+    fs::write(&path_a, lines[..34].join("\n") + "\n").unwrap();
+    fs::write(&path_b, lines[11..].join("\n") + "\n").unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    let result = merge::merge(
+        &mut w,
+        &[path_b.to_str().unwrap(), path_a.to_str().unwrap()],
+    );
+
+    fs::remove_file(&path_a).unwrap();
+    fs::remove_file(&path_b).unwrap();
+    result.unwrap();
+    assert_eq!(String::from_utf8(w).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_trailing_comments() {
+    let path_a = env::temp_dir().join("timetrack_test_merge_trailing_a.csv");
+    let path_b = env::temp_dir().join("timetrack_test_merge_trailing_b.csv");
+    fs::write(
+        &path_a,
+        "2019-11-26T07:00+0100\ton\n2019-11-26T12:00+0100\toff\n# end of a\n",
+    )
+    .unwrap();
+    fs::write(
+        &path_b,
+        "2019-11-27T07:00+0100\ton\n2019-11-27T12:00+0100\toff\n\n# end of b\n",
+    )
+    .unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    let result = merge::merge(
+        &mut w,
+        &[path_b.to_str().unwrap(), path_a.to_str().unwrap()],
+    );
+
+    fs::remove_file(&path_a).unwrap();
+    fs::remove_file(&path_b).unwrap();
+    result.unwrap();
+    assert_eq!(
+        String::from_utf8(w).unwrap(),
+        "2019-11-26T07:00+0100\ton\n\
+         2019-11-26T12:00+0100\toff\n\
+         # end of a\n\
+         2019-11-27T07:00+0100\ton\n\
+         2019-11-27T12:00+0100\toff\n\
+         \n\
+         # end of b\n"
+    );
+}
+
+#[test]
+fn test_merge_overlap() {
+    let path_a = env::temp_dir().join("timetrack_test_merge_overlap_a.csv");
+    let path_b = env::temp_dir().join("timetrack_test_merge_overlap_b.csv");
+    fs::write(
+        &path_a,
+        "2019-11-26T07:00+0100\ton\n2019-11-26T12:00+0100\toff\n",
+    )
+    .unwrap();
+    fs::write(
+        &path_b,
+        "2019-11-26T11:00+0100\ton\n2019-11-26T13:00+0100\toff\n",
+    )
+    .unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    let file_a = path_a.to_str().unwrap();
+    let file_b = path_b.to_str().unwrap();
+    let result = merge::merge(&mut w, &[file_a, file_b]);
+
+    fs::remove_file(&path_a).unwrap();
+    fs::remove_file(&path_b).unwrap();
+    assert_eq!(
+        result,
+        Err(format!(
+            "Could not merge files:\n\
+             Overlapping work times: 2019-11-26 07:00 – 12:00 in {} and 2019-11-26 11:00 – 13:00 in {}",
+            file_a, file_b
+        ))
+    );
+    assert!(w.is_empty());
+}
+
+#[test]
+fn test_merge_open_day() {
+    let path_a = env::temp_dir().join("timetrack_test_merge_open_day_a.csv");
+    let path_b = env::temp_dir().join("timetrack_test_merge_open_day_b.csv");
+    fs::write(
+        &path_a,
+        "2019-11-26T08:00+0100\ton\n2019-11-26T09:00+0100\tstart\tA\n",
+    )
+    .unwrap();
+    fs::write(
+        &path_b,
+        "2019-11-26T18:00+0100\ton\n2019-11-26T19:00+0100\toff\n",
+    )
+    .unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    let file_a = path_a.to_str().unwrap();
+    let file_b = path_b.to_str().unwrap();
+    let result = merge::merge(&mut w, &[file_a, file_b]);
+
+    fs::remove_file(&path_a).unwrap();
+    fs::remove_file(&path_b).unwrap();
+    assert_eq!(
+        result,
+        Err(format!(
+            "Could not merge files:\n\
+             Conflicting entry \"2019-11-26T18:00+0100\ton\" in {} line 1: \
+             Invalid event On in state TaskActive",
+            file_b
+        ))
+    );
+}
+
+#[test]
+fn test_append_checks() {
+    let path = env::temp_dir().join("timetrack_test_append_checks.csv");
+    let file = path.to_str().unwrap();
+    fs::write(&path, "2019-11-26T07:00+0100\ton\n").unwrap();
+
+    let time = DateTime::parse_from_rfc3339("2019-11-26T06:59:00+01:00").unwrap();
+    let result = filewrite::append_entry(
+        file,
+        &TimelogEntry::new(&time, LogEvent::Off(None)),
+        &ReadOptions::default(),
+    );
+    assert_eq!(
+        result,
+        Err("Cannot add \"2019-11-26T06:59+0100\toff\": \
+             it is earlier than the last entry \"2019-11-26T07:00+0100\ton\""
+            .to_owned())
+    );
+
+    fs::write(&path, "2019-11-26T07:00+0100\ton").unwrap();
+    let time = DateTime::parse_from_rfc3339("2019-11-26T08:00:00+01:00").unwrap();
+    let result = filewrite::append_entry(
+        file,
+        &TimelogEntry::new(&time, LogEvent::Off(None)),
+        &ReadOptions::default(),
+    );
+    assert!(result
+        .unwrap_err()
+        .contains("does not end with a line break"));
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert_eq!(content, "2019-11-26T07:00+0100\ton");
+}
+
+#[test]
+fn test_append_after_open_day() {
+    let path = env::temp_dir().join("timetrack_test_append_open_day.csv");
+    let file = path.to_str().unwrap();
+    let original = "2019-11-26T07:00+0100\ton\n2019-11-26T08:00+0100\tstart\tA\n";
+    fs::write(&path, original).unwrap();
+
+    let time = DateTime::parse_from_rfc3339("2019-11-27T09:00:00+01:00").unwrap();
+    let result = cmd::append(file, &ReadOptions::default(), LogEvent::On, &time);
+    assert!(result
+        .unwrap_err()
+        .contains("Invalid event On in state TaskActive"));
+
+    let read = ReadOptions {
+        open_days: OpenDayPolicy::LastEntry,
+        ..ReadOptions::default()
+    };
+    let result = cmd::append(file, &read, LogEvent::On, &time);
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert_eq!(result, Ok(()));
+    assert_eq!(
+        content,
+        format!("{}\n# Wednesday\n2019-11-27T09:00+0100\ton\n", original)
+    );
+}
+
+#[test]
+fn test_append_concurrently() {
+    let path = env::temp_dir().join("timetrack_test_append_concurrently.csv");
+    fs::write(&path, "2019-11-26T07:00+0100\ton\n").unwrap();
+
+    let threads: Vec<_> = (0..16)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let time = DateTime::parse_from_rfc3339("2019-11-26T08:00:00+01:00").unwrap();
+                let event = LogEvent::Estimate {
+                    name: format!("Task {}", i),
+                    duration: std::time::Duration::from_secs(3600),
+                };
+                filewrite::append_entry(
+                    path.to_str().unwrap(),
+                    &TimelogEntry::new(&time, event),
+                    &ReadOptions::default(),
+                )
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap().unwrap();
+    }
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert_eq!(content.lines().count(), 17);
+    assert!(content
+        .lines()
+        .skip(1)
+        .all(|line| line.starts_with("2019-11-26T08:00+0100\testimate\tTask ")));
+}
+
+#[test]
+fn test_insert_while_appending() {
+    let path = env::temp_dir().join("timetrack_test_insert_while_appending.csv");
+    fs::write(&path, "2019-11-26T07:00+0100\ton\n").unwrap();
+
+    let threads: Vec<_> = (0..16)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let file = path.to_str().unwrap();
+                let event = LogEvent::Estimate {
+                    name: format!("Task {}", i),
+                    duration: std::time::Duration::from_secs(3600),
+                };
+                if i % 2 == 0 {
+                    let time = DateTime::parse_from_rfc3339("2019-11-26T08:00:00+01:00").unwrap();
+                    cmd::append(file, &ReadOptions::default(), event, &time)
+                } else {
+                    let time = DateTime::parse_from_rfc3339("2019-11-26T07:30:00+01:00").unwrap();
+                    cmd::insert(
+                        file,
+                        &ReadOptions::default(),
+                        &TimelogEntry::new(&time, event),
+                    )
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap().unwrap();
+    }
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert_eq!(content.lines().count(), 17);
+}
+
+#[test]
+fn test_tasks_sorted_and_filtered() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let options = PrintOptions {
+        sort: TaskSort::Duration,
+        filter: Some(Regex::new("^(BACKEND|FRONTEND|n/n)").unwrap()),
+        hide_pseudo: true,
+        shares: false,
+        ..PrintOptions::default()
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::tasks(&mut w, file, &ReadOptions::default(), &options).unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(
+        result,
+        "#\ttime\ttask name\n\
+         5\t02:39\tBACKEND - logging framework\n\
+         9\t00:41\tFRONTEND - release notes\n\
+         6\t00:18\tFRONTEND - translations\n\
+         \t08:24\ttotal work time\n"
+    );
+}
+
+#[test]
+fn test_overtime_alert() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let render = |target: u64| {
+        let options = PrintOptions {
+            filter: Some(Regex::new("^$").unwrap()),
+            format: TableFormat::Colored,
+            target: Some(Duration::from_secs(target * 3600)),
+            ..PrintOptions::default()
+        };
+        let mut w: Vec<u8> = Vec::new();
+        cmd::tasks(&mut w, file, &ReadOptions::default(), &options).unwrap();
+        String::from_utf8(w).unwrap()
+    };
+
+    assert_eq!(
+        render(8),
+        "\x1b[1m#  time   task name\x1b[0m\n\
+         \x1b[31m   08:24  total work time\x1b[0m\n"
+    );
+    assert_eq!(
+        render(9),
+        "\x1b[1m#  time   task name\x1b[0m\n   \
+         08:24  total work time\n"
+    );
+}
+
+#[test]
+fn test_summary_with_shares() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let options = PrintOptions {
+        filter: Some(Regex::new("^(Pause|BACKEND)").unwrap()),
+        shares: true,
+        ..PrintOptions::default()
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::summaries(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        SummaryScope::Last(1),
+        &options,
+    )
+    .unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(
+        result,
+        "=== 2019-11-28\n\
+         \t00:48\t\t9%\t\tPause\n\
+         5\t02:39\t32%\t29%\t██████▎\tBACKEND - logging framework\n\
+         -- Work time: 08:24\n\
+         -- Work hours:\n\
+         on   \toff  \ttime \tpause\n\
+         08:55\t12:05\t03:10\t\n\
+         12:53\t18:07\t05:14\t00:48\n\
+         \n"
+    );
+}
+
+#[test]
+fn test_tasks_at_fixed_time() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let read = ReadOptions {
+        clock: Clock::Fixed(DateTime::parse_from_rfc3339("2019-11-28T14:00:00+01:00").unwrap()),
+        ..ReadOptions::default()
+    };
+    let options = PrintOptions {
+        filter: Some(Regex::new("^Sprint").unwrap()),
+        template: Some(
+            "{work_time};{end}\n{tasks}{time}{active};{name}\n{end_tasks}"
+                .parse()
+                .unwrap(),
+        ),
+        ..PrintOptions::default()
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::tasks(&mut w, file, &read, &options).unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(
+        result,
+        "04:17;14:00\n\
+         00:45;Sprint planning\n\
+         01:02*;Sprint Retro\n"
+    );
+}
+
+#[test]
+fn test_leave() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let read = ReadOptions {
+        clock: Clock::Fixed(DateTime::parse_from_rfc3339("2019-11-28T14:00:00+01:00").unwrap()),
+        ..ReadOptions::default()
+    };
+    let target = Duration::from_secs(8 * 3600);
+    let mut w: Vec<u8> = Vec::new();
+    cmd::leave(&mut w, file, &read, &target, &PrintOptions::default()).unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(
+        result,
+        "Target 08:00 reached at 17:43\n\
+         Week balance +00:54 evened out at 16:49\n"
+    );
+
+    let read = ReadOptions {
+        clock: Clock::Fixed(DateTime::parse_from_rfc3339("2019-11-29T09:00:00+01:00").unwrap()),
+        ..ReadOptions::default()
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::leave(&mut w, file, &read, &target, &PrintOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(w).unwrap(), "No work day started today\n");
+}
+
+#[test]
+fn test_leave_with_split_day() {
+    let path = env::temp_dir().join("timetrack_test_leave_split_day.csv");
+    let file = path.to_str().unwrap();
+    fs::write(
+        &path,
+        "2019-11-25T08:00+0100\ton\n\
+         2019-11-25T09:00+0100\toff\n\
+         2019-11-26T08:00+0100\ton\n\
+         2019-11-26T08:00+0100\tstart\tA\n\
+         2019-11-26T12:00+0100\toff\n\
+         2019-11-26T13:00+0100\ton\n\
+         2019-11-26T13:00+0100\tstart\tB\n",
+    )
+    .unwrap();
+
+    let read = ReadOptions {
+        clock: Clock::Fixed(DateTime::parse_from_rfc3339("2019-11-26T14:00:00+01:00").unwrap()),
+        ..ReadOptions::default()
+    };
+    let target = Duration::from_secs(8 * 3600);
+    let mut w: Vec<u8> = Vec::new();
+    let result = cmd::leave(&mut w, file, &read, &target, &PrintOptions::default());
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+
+    assert_eq!(
+        String::from_utf8(w).unwrap(),
+        "Target 08:00 reached at 17:00\n\
+         Week balance -07:00 evened out at 2019-11-27 00:00\n"
+    );
+}
+
+#[test]
+fn test_week() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let expected_path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "expected",
+        "week_48.txt",
+    ]
+    .iter()
+    .collect();
+    let expected = fs::read_to_string(expected_path).unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    cmd::week(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        NaiveDate::from_ymd(2019, 11, 25),
+        &PrintOptions::default(),
+    )
+    .unwrap();
 
     let result = String::from_utf8(w).unwrap();
 
     assert_eq!(result, expected);
 }
+
+#[test]
+fn test_week_with_split_day() {
+    let path = env::temp_dir().join("timetrack_test_week_split_day.csv");
+    let file = path.to_str().unwrap();
+    fs::write(
+        &path,
+        "2019-11-26T08:00+0100\ton\n\
+         2019-11-26T08:00+0100\tstart\tA\n\
+         2019-11-26T12:00+0100\toff\n\
+         2019-11-26T13:00+0100\ton\n\
+         2019-11-26T13:00+0100\tstart\tB\n\
+         2019-11-26T17:00+0100\toff\n",
+    )
+    .unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    let result = cmd::week(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        NaiveDate::from_ymd(2019, 11, 25),
+        &PrintOptions::default(),
+    );
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+    let lines: Vec<&str> = result.lines().collect();
+    assert_eq!(lines[2], "times\t\t08:00-12:00\t\t\t\t\t");
+    assert_eq!(lines[3], "\t\t13:00-17:00\t\t\t\t\t");
+    assert_eq!(lines[4], "total\t\t08:00\t\t\t\t\t");
+    assert_eq!(lines[5], "tasks\t\t04:00 A\t\t\t\t\t");
+    assert_eq!(lines[6], "\t\t04:00 B\t\t\t\t\t");
+}
+
+#[test]
+fn test_heatmap() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let expected_path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "expected",
+        "heatmap_2019.txt",
+    ]
+    .iter()
+    .collect();
+    let expected = fs::read_to_string(expected_path).unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    cmd::heatmap(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        2019,
+        &Duration::from_secs(8 * 3600),
+        &PrintOptions::default(),
+    )
+    .unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(result, expected);
+
+    for year in [300_000, 262_143, -262_144] {
+        let mut w: Vec<u8> = Vec::new();
+        let result = cmd::heatmap(
+            &mut w,
+            file,
+            &ReadOptions::default(),
+            year,
+            &Duration::from_secs(8 * 3600),
+            &PrintOptions::default(),
+        );
+        assert_eq!(result, Err(format!("Invalid year given: {}", year)));
+        assert!(w.is_empty());
+    }
+}
+
+#[test]
+fn test_focus() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let range = DateRange {
+        from: Some(NaiveDate::from_ymd(2019, 11, 22)),
+        to: None,
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::focus(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        &range,
+        &PrintOptions::default(),
+    )
+    .unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(
+        result,
+        "=== Focus 2019-11-22 – …\n\
+         -- Days:\n\
+         date\tswitches\tlongest\taverage\tunder 00:15\n\
+         2019-11-22\t4\t01:47\t00:42\t00:28 (7%)\n\
+         2019-11-26\t17\t01:56\t00:27\t01:09 (14%)\n\
+         2019-11-28\t11\t01:45\t00:37\t00:22 (5%)\n\
+         -- Weeks:\n\
+         week\tdays\tswitches/day\tlongest\taverage\tunder 00:15\n\
+         2019-W47\t1\t4.0\t01:47\t00:42\t00:28 (7%)\n\
+         2019-W48\t2\t14.0\t01:56\t00:31\t01:31 (10%)\n"
+    );
+}
+
+#[test]
+fn test_tasks_template() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let template = "{date};{work_time};{intervals}\n{tasks}{n};{time};{name}\n{end_tasks}";
+    let options = PrintOptions {
+        filter: Some(Regex::new("^(Pause|FRONTEND)").unwrap()),
+        template: Some(template.parse().unwrap()),
+        ..PrintOptions::default()
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::tasks(&mut w, file, &ReadOptions::default(), &options).unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+
+    assert_eq!(
+        result,
+        "2019-11-28;08:24;08:55-12:05, 12:53-18:07\n\
+         6;00:18;FRONTEND - translations\n\
+         9;00:41;FRONTEND - release notes\n"
+    );
+}
+
+#[test]
+fn test_tasks_template_lists_table_tasks() {
+    let d: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "resources",
+        "timetrack.csv",
+    ]
+    .iter()
+    .collect();
+    let file = d.to_str().unwrap();
+
+    let mut table: Vec<u8> = Vec::new();
+    cmd::tasks(
+        &mut table,
+        file,
+        &ReadOptions::default(),
+        &PrintOptions::default(),
+    )
+    .unwrap();
+    let table = String::from_utf8(table).unwrap();
+
+    let options = PrintOptions {
+        template: Some("{tasks}{n};{name}\n{end_tasks}".parse().unwrap()),
+        ..PrintOptions::default()
+    };
+    let mut w: Vec<u8> = Vec::new();
+    cmd::tasks(&mut w, file, &ReadOptions::default(), &options).unwrap();
+    let listed: Vec<(String, String)> = String::from_utf8(w)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let (n, name) = line.split_once(';').unwrap();
+            (n.to_string(), name.to_string())
+        })
+        .collect();
+
+    assert!(!table.contains("Pause"));
+    assert!(listed.iter().all(|(_, name)| name != "Pause"));
+    let rows: Vec<Vec<&str>> = table
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .filter(|row: &Vec<&str>| row.first().is_some_and(|n| n.parse::<usize>().is_ok()))
+        .collect();
+    assert_eq!(listed.len(), rows.len());
+    for ((n, name), row) in listed.iter().zip(&rows) {
+        assert_eq!(n, row[0]);
+        assert_eq!(name, &row[2..].join(" "));
+    }
+}
+
+#[test]
+fn test_insert() {
+    let path = env::temp_dir().join("timetrack_test_insert.csv");
+    let file = path.to_str().unwrap();
+    let original = "\n# Tuesday\n\
+                    2019-11-26T07:00+0100\ton\n\
+                    2019-11-26T08:00+0100\tstart\tA\n\
+                    2019-11-26T12:00+0100\toff\n\
+                    \n# Wednesday\n\
+                    2019-11-27T07:00+0100\ton\n";
+    fs::write(&path, original).unwrap();
+
+    let time = DateTime::parse_from_rfc3339("2019-11-26T13:00:00+01:00").unwrap();
+    let result = cmd::insert(
+        file,
+        &ReadOptions::default(),
+        &TimelogEntry::new(&time, LogEvent::Stop),
+    );
+    assert_eq!(
+        result,
+        Err("Cannot insert \"2019-11-26T13:00+0100\tstop\": \
+             Invalid event Stop in state Idle (in line 6)"
+            .to_owned())
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+    let time = DateTime::parse_from_rfc3339("2019-11-26T10:15:00+01:00").unwrap();
+    cmd::insert(
+        file,
+        &ReadOptions::default(),
+        &TimelogEntry::new(&time, LogEvent::Start("B".to_owned())),
+    )
+    .unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert_eq!(
+        content,
+        "\n# Tuesday\n\
+         2019-11-26T07:00+0100\ton\n\
+         2019-11-26T08:00+0100\tstart\tA\n\
+         2019-11-26T10:15+0100\tstart\tB\n\
+         2019-11-26T12:00+0100\toff\n\
+         \n# Wednesday\n\
+         2019-11-27T07:00+0100\ton\n"
+    );
+}
+
+#[test]
+fn test_insert_into_open_day() {
+    let path = env::temp_dir().join("timetrack_test_insert_open_day.csv");
+    let file = path.to_str().unwrap();
+    fs::write(
+        &path,
+        "2019-11-26T07:00+0100\ton\n\
+         2019-11-26T08:00+0100\tstart\tA\n\
+         2019-11-27T07:00+0100\ton\n\
+         2019-11-27T16:00+0100\toff\n",
+    )
+    .unwrap();
+
+    let time = DateTime::parse_from_rfc3339("2019-11-26T17:00:00+01:00").unwrap();
+    let result = cmd::insert(
+        file,
+        &ReadOptions::default(),
+        &TimelogEntry::new(&time, LogEvent::Off(None)),
+    );
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert_eq!(result, Ok(()));
+    assert_eq!(
+        content,
+        "2019-11-26T07:00+0100\ton\n\
+         2019-11-26T08:00+0100\tstart\tA\n\
+         2019-11-26T17:00+0100\toff\n\
+         2019-11-27T07:00+0100\ton\n\
+         2019-11-27T16:00+0100\toff\n"
+    );
+}
+
+#[test]
+fn test_insert_into_unordered_log() {
+    let path = env::temp_dir().join("timetrack_test_insert_unordered.csv");
+    let file = path.to_str().unwrap();
+    let original = "2019-11-26T07:00+0100\ton\n\
+                    2019-11-26T09:00+0100\tstart\tA\n\
+                    2019-11-26T08:00+0100\tstop\n";
+    fs::write(&path, original).unwrap();
+
+    let time = DateTime::parse_from_rfc3339("2019-11-26T10:00:00+01:00").unwrap();
+    let result = cmd::insert(
+        file,
+        &ReadOptions::default(),
+        &TimelogEntry::new(&time, LogEvent::Off(None)),
+    );
+
+    let content = fs::read_to_string(&path).unwrap();
+    remove_log(&path);
+    assert_eq!(
+        result,
+        Err("Cannot insert \"2019-11-26T10:00+0100\toff\": \
+             Timestamp in line 3 is earlier than the one in line 2: \
+             \"2019-11-26T08:00+0100\tstop\" after \"2019-11-26T09:00+0100\tstart\tA\""
+            .to_owned())
+    );
+    assert_eq!(content, original);
+}
+
+#[test]
+fn test_audit() {
+    let path = env::temp_dir().join("timetrack_test_audit.csv");
+    let file = path.to_str().unwrap();
+    fs::write(
+        &path,
+        "\n# Monday\n\
+         2019-11-25T07:00+0100\ton\n\
+         2019-11-25T19:00+0100\toff\n\
+         \n# Tuesday\n\
+         2019-11-26T08:00+0100\ton\n\
+         2019-11-26T08:30+0100\tstart\tA\n\
+         2019-11-26T08:30+0100\tstart\tB\n\
+         2019-11-26T09:00+0100\tstop\n\
+         2019-11-26T11:00+0100\toff\n\
+         \n# Wednesday\n\
+         2019-11-27T08:00+0100\ton\n\
+         2019-11-27T08:10+0100\tstart\tA\n",
+    )
+    .unwrap();
+
+    let mut w: Vec<u8> = Vec::new();
+    audit::audit(
+        &mut w,
+        file,
+        &ReadOptions::default(),
+        &AuditOptions::default(),
+    )
+    .unwrap();
+
+    let mut human: Vec<u8> = Vec::new();
+    let options = AuditOptions {
+        duration_format: DurationFormat::Human,
+        ..AuditOptions::default()
+    };
+    audit::audit(&mut human, file, &ReadOptions::default(), &options).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let result = String::from_utf8(w).unwrap();
+    let expected = format!(
+        "{0}:3: 2019-11-25: session from 07:00 to 19:00 lasts 12:00, more than 10:00\n\
+         {0}:3: 2019-11-25: 12:00 of work time is not assigned to a task, more than 01:00\n\
+         {0}:3: 2019-11-25: no tasks recorded\n\
+         {0}:7: 2019-11-26: 02:30 of work time is not assigned to a task, more than 01:00\n\
+         {0}:8: 2019-11-26: task \"A\" lasts less than a minute\n\
+         {0}:15: 2019-11-27: day was never ended, \"off\" is missing after 08:10\n",
+        file
+    );
+    assert_eq!(result, expected);
+
+    let result = String::from_utf8(human).unwrap();
+    assert!(result.contains("session from 07:00 to 19:00 lasts 12h, more than 10h\n"));
+    assert!(result.contains("2h30m of work time is not assigned to a task, more than 1h\n"));
+}
+
+/// Removes a log written by the tests, along with the lock file next to it.
+fn remove_log(path: &Path) {
+    fs::remove_file(path).unwrap();
+    let _ = fs::remove_file(format!("{}.lock", path.display()));
+}